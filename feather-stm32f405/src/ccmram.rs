@@ -33,7 +33,7 @@
 //!     └─ Potential uses: hot-path variables, timing-critical buffers
 //! ```
 //!
-//! Note: TLS buffers (34KB) are now in main SRAM for better size flexibility.
+//! Note: TLS buffers (~8.5KB) are now in main SRAM for better size flexibility.
 //! See `src/tls_buffers.rs` for TLS buffer management.
 //!
 //! # Current Allocations
@@ -42,6 +42,9 @@
 //!   - Tracks whether RTC has been synchronized with NTP
 //!   - Placed in CCM RAM for zero-wait-state access
 //!   - Time itself is stored in hardware RTC peripheral
+//! - **Wall-clock calibration**: 4 × AtomicU32 + AtomicI32 (20 bytes)
+//!   - Unix time and monotonic timer value at the last sync
+//!   - Frequency correction estimated from successive syncs
 //!
 //! # Safety Requirements
 //!
@@ -72,7 +75,7 @@
 #![allow(unsafe_code)]
 #![deny(warnings)]
 
-use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use core::sync::atomic::{AtomicBool, AtomicI32, AtomicU32, Ordering};
 
 /// System time synchronization status in CCM RAM
 #[allow(dead_code)]
@@ -85,7 +88,9 @@ pub static TIME_SYNCED: AtomicBool = AtomicBool::new(false);
 //
 // This implements a high-precision wall-clock time system similar to Linux:
 // - CLOCK_MONOTONIC: TIM2 running at 1MHz (microsecond precision)
-// - CLOCK_REALTIME: CLOCK_MONOTONIC + Unix time offset from NTP calibration
+// - CLOCK_REALTIME: CLOCK_MONOTONIC + Unix time offset from NTP calibration,
+//   with elapsed time scaled by the frequency correction estimated from
+//   successive syncs (see time::drift)
 // - RTC: Backup only (not used for primary timekeeping)
 //
 // When NTP sync occurs, we capture:
@@ -96,7 +101,10 @@ pub static TIME_SYNCED: AtomicBool = AtomicBool::new(false);
 // doesn't have native 64-bit atomics. This limits us but is workable:
 // - Seconds stored as u32 (wraps in 2106, acceptable for embedded systems)
 // - Microseconds within second stored separately (0-999999)
-// - Monotonic ticks in microseconds (wraps after ~71.6 minutes, but handled via wrapping arithmetic)
+// - Monotonic ticks in microseconds split into low and high halves, written
+//   and read together in a critical section. A 32-bit value would wrap after
+//   ~71.6 minutes, and the wall clock must keep running through outages of
+//   any length between syncs.
 
 /// Base Unix time in seconds at calibration
 ///
@@ -113,13 +121,24 @@ static BASE_UNIX_SECS: AtomicU32 = AtomicU32::new(0);
 #[link_section = ".ccmram"]
 static BASE_UNIX_MICROS: AtomicU32 = AtomicU32::new(0);
 
-/// Base monotonic timer value at calibration (microseconds)
+/// Base monotonic timer value at calibration, low 32 bits (microseconds)
 ///
 /// Set during NTP synchronization to the monotonic timer ticks (microseconds)
 /// at the moment of calibration. This is captured at the same instant as BASE_UNIX_SECS/MICROS.
-/// Using u32 for microseconds means it wraps every ~71.6 minutes, but wrapping_sub handles this correctly.
 #[link_section = ".ccmram"]
-static BASE_MONO_MICROS: AtomicU32 = AtomicU32::new(0);
+static BASE_MONO_MICROS_LO: AtomicU32 = AtomicU32::new(0);
+
+/// Base monotonic timer value at calibration, high 32 bits
+#[link_section = ".ccmram"]
+static BASE_MONO_MICROS_HI: AtomicU32 = AtomicU32::new(0);
+
+/// Frequency correction of the monotonic timer (parts per billion)
+///
+/// Positive values mean TIM2 runs slow, so elapsed time is stretched.
+/// Set together with the base values; zero until enough syncs have been
+/// seen to estimate the HSE crystal error.
+#[link_section = ".ccmram"]
+static FREQ_CORRECTION_PPB: AtomicI32 = AtomicI32::new(0);
 
 /// Calibrate the wall-clock time system
 ///
@@ -130,17 +149,24 @@ static BASE_MONO_MICROS: AtomicU32 = AtomicU32::new(0);
 /// * `unix_secs` - Unix epoch time in seconds from NTP
 /// * `unix_micros` - Microseconds within the second (0-999999)
 /// * `mono_micros` - Monotonic timer ticks in microseconds at calibration moment
+/// * `freq_ppb` - Frequency correction applied from now on (parts per billion)
 #[allow(dead_code)]
-pub fn calibrate_wallclock(unix_secs: u32, unix_micros: u32, mono_micros: u32) {
-    BASE_UNIX_SECS.store(unix_secs, Ordering::Release);
-    BASE_UNIX_MICROS.store(unix_micros, Ordering::Release);
-    BASE_MONO_MICROS.store(mono_micros, Ordering::Release);
-    TIME_SYNCED.store(true, Ordering::Release);
+pub fn calibrate_wallclock(unix_secs: u32, unix_micros: u32, mono_micros: u64, freq_ppb: i32) {
+    // Readers must never see a mix of old and new base values
+    critical_section::with(|_| {
+        BASE_UNIX_SECS.store(unix_secs, Ordering::Release);
+        BASE_UNIX_MICROS.store(unix_micros, Ordering::Release);
+        BASE_MONO_MICROS_LO.store(mono_micros as u32, Ordering::Release);
+        BASE_MONO_MICROS_HI.store((mono_micros >> 32) as u32, Ordering::Release);
+        FREQ_CORRECTION_PPB.store(freq_ppb, Ordering::Release);
+        TIME_SYNCED.store(true, Ordering::Release);
+    });
 }
 
 /// Get current Unix time in seconds and microseconds
 ///
-/// Computes CLOCK_REALTIME as: base_unix + (current_mono - base_mono)
+/// Computes CLOCK_REALTIME as:
+/// base_unix + (current_mono - base_mono) * (1 + freq_correction)
 ///
 /// Returns (0, 0) if not yet calibrated (TIME_SYNCED == false).
 ///
@@ -150,21 +176,32 @@ pub fn calibrate_wallclock(unix_secs: u32, unix_micros: u32, mono_micros: u32) {
 /// # Returns
 /// Tuple of (unix_seconds, microseconds) or (0, 0) if not calibrated
 #[allow(dead_code)]
-pub fn now_unix_time(current_mono_micros: u32) -> (u32, u32) {
+pub fn now_unix_time(current_mono_micros: u64) -> (u32, u32) {
     if !TIME_SYNCED.load(Ordering::Acquire) {
         return (0, 0); // Not yet calibrated
     }
 
-    let base_secs = BASE_UNIX_SECS.load(Ordering::Acquire);
-    let base_micros = BASE_UNIX_MICROS.load(Ordering::Acquire);
-    let base_mono = BASE_MONO_MICROS.load(Ordering::Acquire);
+    let (base_secs, base_micros, base_mono, freq_ppb) = critical_section::with(|_| {
+        let mono_lo = BASE_MONO_MICROS_LO.load(Ordering::Acquire);
+        let mono_hi = BASE_MONO_MICROS_HI.load(Ordering::Acquire);
+        (
+            BASE_UNIX_SECS.load(Ordering::Acquire),
+            BASE_UNIX_MICROS.load(Ordering::Acquire),
+            (u64::from(mono_hi) << 32) | u64::from(mono_lo),
+            FREQ_CORRECTION_PPB.load(Ordering::Acquire),
+        )
+    });
+
+    // Compute elapsed time since calibration (the 64-bit timer never wraps)
+    let elapsed_micros = current_mono_micros.saturating_sub(base_mono);
 
-    // Compute elapsed time since calibration (handling wrap-around)
-    let elapsed_micros = current_mono_micros.wrapping_sub(base_mono);
+    // Correct for the timer's frequency error (at most ±500 ppm)
+    let correction = i128::from(elapsed_micros) * i128::from(freq_ppb) / 1_000_000_000;
+    let elapsed_micros = (i128::from(elapsed_micros) + correction).max(0) as u64;
 
     // Convert elapsed microseconds to seconds and remaining microseconds
-    let elapsed_secs = elapsed_micros / 1_000_000;
-    let elapsed_micros_remainder = elapsed_micros % 1_000_000;
+    let elapsed_secs = (elapsed_micros / 1_000_000) as u32;
+    let elapsed_micros_remainder = (elapsed_micros % 1_000_000) as u32;
 
     // Add to base time
     let total_micros = base_micros + elapsed_micros_remainder;
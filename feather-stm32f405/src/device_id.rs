@@ -26,8 +26,7 @@ use heapless::String;
 
 /// Maximum length of the client ID string
 /// Format: "stm32f405-" (10 chars) + 24 hex chars = 34 chars total
-#[allow(dead_code)]
-const CLIENT_ID_MAX_LEN: usize = 34;
+pub const CLIENT_ID_MAX_LEN: usize = 34;
 
 /// Get the STM32F405 unique device ID as a hex string
 ///
@@ -22,6 +22,7 @@ mod app {
     use super::*;
     use defmt::{error, info, warn};
     use embassy_futures::join::join3;
+    use embassy_futures::select::{select, Either};
     use embassy_stm32::exti::ExtiInput;
     use embassy_stm32::gpio::{Level, Output, Pull, Speed};
     use embassy_stm32::peripherals;
@@ -30,7 +31,12 @@ mod app {
     use embassy_stm32::spi::{self, Spi};
     use embassy_stm32::time::Hertz;
 
-    use network::{manager, NetworkClient, SntpClient};
+    use network::mqtt::inbound::{InboundReceiver, InboundSender, INBOUND_QUEUE_DEPTH};
+    use network::mqtt::supervisor::{self, OutboundReceiver, OutboundSender, OUTBOUND_QUEUE_DEPTH};
+    use network::{
+        manager, DeliverySignal, InboundMessage, NetworkClient, OutboundMessage, SntpClient,
+    };
+    use rust_mqtt::types::QoS;
 
     type SpiPeripheral = embassy_stm32::Peri<'static, peripherals::SPI2>;
     type PinPB13 = embassy_stm32::Peri<'static, peripherals::PB13>;
@@ -139,8 +145,17 @@ mod app {
             dma_rx: p.DMA1_CH3,
         };
 
+        // Outbound MQTT queue: producer tasks -> MQTT supervisor in network_task
+        let (outbound_tx, outbound_rx) =
+            rtic_sync::make_channel!(OutboundMessage, OUTBOUND_QUEUE_DEPTH);
+        // Inbound command queue: MQTT supervisor -> command task
+        let (command_tx, command_rx) =
+            rtic_sync::make_channel!(InboundMessage, INBOUND_QUEUE_DEPTH);
+
         heartbeat::spawn().ok();
-        network_task::spawn(net_periph, p.RNG).ok();
+        telemetry::spawn(outbound_tx).ok();
+        command::spawn(command_rx).ok();
+        network_task::spawn(net_periph, p.RNG, outbound_rx, command_tx).ok();
 
         (Shared {}, Local { led })
     }
@@ -157,6 +172,102 @@ mod app {
         }
     }
 
+    /// Telemetry task - queues a test message for MQTT every 30 seconds
+    ///
+    /// Messages are dropped (QoS 0 semantics) while the queue is full, e.g.
+    /// during a broker outage.
+    #[task(priority = 1)]
+    async fn telemetry(_cx: telemetry::Context, mut outbound: OutboundSender) {
+        use core::fmt::Write;
+
+        static TELEMETRY_DELIVERY: DeliverySignal = DeliverySignal::new();
+
+        info!("Telemetry task started (30s interval)");
+
+        let topic = match network::mqtt::device_topic("telemetry") {
+            Ok(topic) => topic,
+            Err(e) => {
+                error!("Failed to format MQTT topic: {:?}", e);
+                return;
+            }
+        };
+
+        let mut message_counter = 0u32;
+        loop {
+            Mono::delay(30_000.millis()).await;
+            message_counter += 1;
+
+            // Build payload (simple JSON for now)
+            // Format: {"msg_id":N,"timestamp":UNIX_SECS,"micros":MICROS}
+            let timestamp = time::get_timestamp();
+            let mut payload = heapless::String::<128>::new();
+            if write!(
+                &mut payload,
+                "{{\"msg_id\":{},\"timestamp\":{},\"micros\":{}}}",
+                message_counter, timestamp.unix_secs, timestamp.micros
+            )
+            .is_err()
+            {
+                error!("Failed to format payload JSON");
+                continue;
+            }
+
+            // QoS 1 (AtLeastOnce) per SR-SENS-004
+            let msg = match OutboundMessage::new(
+                topic.as_str(),
+                payload.as_bytes(),
+                QoS::AtLeastOnce,
+                false,
+            ) {
+                Ok(msg) => msg,
+                Err(e) => {
+                    error!("Failed to build message #{}: {:?}", message_counter, e);
+                    continue;
+                }
+            };
+
+            // Wait for the PUBACK, but never longer than one telemetry interval.
+            // A message still in flight on timeout is retransmitted by the
+            // supervisor after the next reconnect; its late PUBACK carries the
+            // old message's tag and is not mistaken for the next one's.
+            let confirmed = Mono::timeout_after(
+                25_000.millis(),
+                supervisor::publish_confirmed(&mut outbound, msg, &TELEMETRY_DELIVERY),
+            )
+            .await;
+
+            match confirmed {
+                Ok(Ok(())) => info!(
+                    "Message #{} acknowledged on '{}' ({} bytes)",
+                    message_counter,
+                    topic.as_str(),
+                    payload.len()
+                ),
+                Ok(Err(e)) => warn!("Message #{} not delivered: {:?}", message_counter, e),
+                Err(_) => warn!("Message #{} not acknowledged yet", message_counter),
+            }
+        }
+    }
+
+    /// Command task - handles messages on `device/{id}/cmd/#`
+    #[task(priority = 1)]
+    async fn command(_cx: command::Context, mut commands: InboundReceiver) {
+        info!("Command task started");
+        while let Ok(message) = commands.recv().await {
+            let topic = message.topic.as_str();
+            let command = topic.rsplit('/').next().unwrap_or(topic);
+            info!(
+                "Command '{}' received ({} bytes payload)",
+                command,
+                message.payload.len()
+            );
+
+            // No commands are implemented yet
+            warn!("Unknown command '{}'", command);
+        }
+        warn!("Command channel closed");
+    }
+
     /// Network task - orchestrates network stack and protocol clients
     ///
     /// Stack is !Send and must remain within this task.
@@ -165,6 +276,8 @@ mod app {
         _cx: network_task::Context,
         periph: NetworkPeripherals,
         rng_periph: embassy_stm32::Peri<'static, peripherals::RNG>,
+        mut outbound: OutboundReceiver,
+        command_tx: InboundSender,
     ) -> ! {
         use embassy_net::{Config, StackResources};
         use static_cell::StaticCell;
@@ -199,7 +312,8 @@ mod app {
         let mac_addr = [0x02, 0x00, 0x00, 0x12, 0x34, 0x56];
         let (device, w5500_runner) = eth::init_w5500(eth_periph, mac_addr).await;
 
-        static RESOURCES: StaticCell<StackResources<3>> = StaticCell::new();
+        // DHCP, DNS, the MQTT TCP socket and the concurrent SNTP UDP socket
+        static RESOURCES: StaticCell<StackResources<4>> = StaticCell::new();
         let (stack, mut net_runner) = embassy_net::new(
             device,
             Config::dhcpv4(Default::default()),
@@ -210,7 +324,7 @@ mod app {
 
         let app_logic = async {
             manager::wait_for_config(&stack).await;
-            run_clients(&stack, rng_periph).await;
+            run_clients(&stack, rng_periph, &mut outbound, command_tx).await;
         };
 
         join3(w5500_runner.run(), net_runner.run(), app_logic).await;
@@ -219,6 +333,8 @@ mod app {
     async fn run_clients(
         stack: &embassy_net::Stack<'static>,
         rng_periph: embassy_stm32::Peri<'static, peripherals::RNG>,
+        outbound: &mut OutboundReceiver,
+        command_tx: InboundSender,
     ) -> ! {
         use embassy_stm32::rng::Rng;
         use static_cell::StaticCell;
@@ -228,9 +344,14 @@ mod app {
         // Initial SNTP sync
         info!("Initializing SNTP time synchronization with RTC (LSE)...");
         match sntp.run(stack).await {
-            Ok(ts) => info!(
-                "SNTP sync successful: {}.{:06} UTC (written to internal RTC)",
-                ts.unix_secs, ts.micros
+            Ok(sync) => info!(
+                "SNTP sync successful: {}.{:06} UTC (written to internal RTC), \
+                 jitter {} µs, {} of {} servers agree",
+                sync.timestamp.unix_secs,
+                sync.timestamp.micros,
+                sync.quality.jitter_us,
+                sync.quality.truechimers,
+                sync.quality.truechimers + sync.quality.falsetickers
             ),
             Err(e) => warn!("SNTP initialization failed: {:?}", e),
         }
@@ -247,6 +368,16 @@ mod app {
             server_name: "192.168.1.1",
             server_port: 8883,
             verify_server: false, // Phase 1: skip verification
+            ca_certificates: &[],
+            pins: &[],
+            client_identity: None,
+            psk: None,
+            cipher_suite: network::CipherSuite::Aes128GcmSha256,
+            max_fragment_length: crate::tls_buffers::FRAGMENT_LENGTH,
+            alpn_protocols: &[],
+            fallback_port: None,
+            tcp: network::TcpConfig::default(),
+            handshake_timeout_ms: 15_000,
         };
         let tls_client = network::tls::TlsClient::new(tls_config);
         match tls_client.test_handshake(stack, &mut rng).await {
@@ -255,7 +386,7 @@ mod app {
         }
 
         // Phase 2: MQTT Connection with Persistent Publishing
-        info!("Establishing persistent MQTT connection over TLS 1.3...");
+        info!("Starting MQTT supervisor over TLS 1.3...");
 
         // Allocate MQTT buffers using StaticCell (RTIC 2.x async task pattern)
         //
@@ -267,63 +398,85 @@ mod app {
         // 4. RTIC 2.x async tasks that never return can safely use function-local statics
         //
         // This follows the same pattern as the embassy_net StackResources allocation.
-        static MQTT_BUFFER: StaticCell<[u8; 2048]> = StaticCell::new();
-        static TCP_RX_BUFFER: StaticCell<[u8; 4096]> = StaticCell::new();
-        static TCP_TX_BUFFER: StaticCell<[u8; 4096]> = StaticCell::new();
-
-        let mqtt_buffer = MQTT_BUFFER.init([0u8; 2048]);
-        let tcp_rx_buffer = TCP_RX_BUFFER.init([0u8; 4096]);
-        let tcp_tx_buffer = TCP_TX_BUFFER.init([0u8; 4096]);
+        // The supervisor reuses the same storage for every reconnect.
+        static MQTT_STORAGE: StaticCell<network::mqtt::MqttStorage> = StaticCell::new();
+        let mqtt_storage = MQTT_STORAGE.init(network::mqtt::MqttStorage::new());
 
         let mqtt_config = network::MqttConfig {
             broker_host: "192.168.1.1",
             broker_port: 8883,
             keep_alive_secs: 60,
-            clean_start: true,
+            // Keep the broker session (and QoS 1/2 state) for 10 minutes
+            // so unacknowledged telemetry can be resumed after a reconnect
+            clean_start: false,
+            session_expiry_secs: 600,
+            // Retained "offline" will and "online" birth on device/{id}/status
+            will: Some(network::mqtt::WillConfig::offline_status(5)),
+            birth_message: true,
+            // Broker credentials are taken from the build environment so they
+            // never end up in the repository
+            username: option_env!("MQTT_USERNAME"),
+            password: option_env!("MQTT_PASSWORD").map(str::as_bytes),
+            // Local test broker: no CA bundle, so the broker certificate is
+            // not verified. For production brokers list the root CA here,
+            // e.g. `&[include_bytes!("../certs/ca.der")]`.
+            ca_certificates: &[],
+            // Alternatively pin the broker key: `&[network::Pin::PublicKey([..])]`
+            pins: &[],
+            // Mutual TLS: set to `Some(network::ClientIdentity { .. })`
+            // with the device certificate and PKCS#8 key (DER) to
+            // authenticate with a client certificate instead of a password
+            client_identity: None,
+            // Edge gateways using TLS-PSK: `Some(network::PskCredentials { .. })`
+            psk: None,
+            // AES-256-GCM-SHA384 is available for SR-SEC-001/002 deployments
+            cipher_suite: network::CipherSuite::Aes128GcmSha256,
+            // 4 KB records keep the TLS buffers at ~8.5 KB (SR-PERF-007)
+            max_fragment_length: crate::tls_buffers::FRAGMENT_LENGTH,
+            // AWS IoT Core behind firewalls blocking 8883: offer
+            // `network::mqtt::AWS_IOT_ALPN_PROTOCOLS` and fall back to 443
+            alpn_protocols: &[],
+            fallback_port: None,
+            // Bounded connect, write and handshake; reads stay open-ended
+            // because MQTT keep-alive detects a silent broker
+            tcp: network::TcpConfig {
+                connect_timeout_ms: 10_000,
+                read_timeout_ms: None,
+                write_timeout_ms: Some(10_000),
+                keep_alive_ms: Some(30_000),
+            },
+            handshake_timeout_ms: 15_000,
+            reconnect: network::BackoffConfig {
+                initial_ms: 1_000,
+                max_ms: 60_000,
+            },
         };
-        let mut mqtt_client = network::MqttClient::new(mqtt_config);
-
-        // Establish persistent MQTT connection using static buffers
-        match mqtt_client
-            .connect_with_buffers(stack, &mut rng, mqtt_buffer, tcp_rx_buffer, tcp_tx_buffer)
-            .await
-        {
-            Ok(()) => {
-                info!("MQTT connection test PASSED ✓");
-                info!("Persistent connection maintained with static buffers");
+        let supervisor = network::MqttSupervisor::new(mqtt_config);
+
+        // Command topics: device/{id}/cmd/# -> command task
+        let mut subscriptions = network::Subscriptions::new();
+        match network::mqtt::device_topic_filter("cmd/#") {
+            Ok(filter) => {
+                if let Err(e) = subscriptions.add(
+                    filter.as_str(),
+                    QoS::AtMostOnce,
+                    network::Route::Channel(command_tx),
+                ) {
+                    error!("Failed to register command subscription: {:?}", e);
+                }
             }
-            Err(e) => warn!("MQTT connection test FAILED: {:?}", e),
+            Err(e) => error!("Failed to format command topic filter: {:?}", e),
         }
 
         info!("Network initialization complete - entering persistent MQTT mode");
 
-        // Run MQTT persistent publishing with periodic SNTP resync
-        // The MQTT client will publish messages every 30 seconds
-        // SNTP will resync every 15 minutes (900 seconds)
-
-        // Create MQTT client for persistent publishing
-        let mut mqtt_persistent = network::MqttClient::new(mqtt_config);
-
-        // Run persistent MQTT publishing loop (30 second interval)
-        // This function maintains the connection and publishes periodically
-        // Note: This function never returns under normal operation
-        info!("Starting persistent MQTT publishing loop (30s interval)");
-
-        // TODO: Add concurrent SNTP resync task with select! macro
-        // For now, just run MQTT publishing - SNTP resync can be added later
-        match mqtt_persistent
-            .run_with_periodic_publish(stack, &mut rng, 30)
-            .await
-        {
-            Ok(()) => warn!("MQTT publishing loop exited unexpectedly"),
-            Err(e) => error!("MQTT publishing loop failed: {:?}", e),
-        }
-
-        // If we get here, the MQTT loop has exited - enter fallback loop
-        warn!("Entering fallback mode - MQTT connection lost");
-        loop {
-            Mono::delay(60_000.millis()).await;
-            warn!("MQTT connection lost - system in fallback mode");
+        // The supervisor publishes queued messages, keeps the session alive and
+        // reconnects with exponential back-off. SNTP resyncs alongside it on
+        // its own adaptive schedule (every 15 minutes once stable).
+        // Neither returns.
+        let mqtt = supervisor.run(stack, &mut rng, mqtt_storage, outbound, &mut subscriptions);
+        match select(mqtt, sntp.resync(stack)).await {
+            Either::First(never) | Either::Second(never) => never,
         }
     }
 
@@ -0,0 +1,113 @@
+#![deny(unsafe_code)]
+#![deny(warnings)]
+//! Jittered exponential back-off for reconnect and retry loops
+//!
+//! Implements SR-ERR-002 (exponential back-off for transient failures). Delays
+//! double with every failed attempt up to a configurable cap, and each delay is
+//! randomised with "equal jitter" (half fixed, half random) so a fleet of
+//! devices that lose the broker at the same moment does not reconnect in
+//! lockstep. Randomness comes from the caller's RNG, which on target is the
+//! STM32 hardware RNG (SR-SEC-003).
+
+use embassy_time::Duration;
+
+use super::config::BackoffConfig;
+
+/// Exponential back-off state
+///
+/// # Example
+///
+/// ```no_run
+/// let mut backoff = Backoff::new(BackoffConfig::default());
+/// loop {
+///     match try_connect().await {
+///         Ok(session) => { backoff.reset(); /* ... */ }
+///         Err(_) => Timer::after(backoff.next_delay(&mut rng)).await,
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Backoff {
+    config: BackoffConfig,
+    attempt: u32,
+}
+
+impl Backoff {
+    /// Create a new back-off sequence starting at the first attempt
+    pub const fn new(config: BackoffConfig) -> Self {
+        Self { config, attempt: 0 }
+    }
+
+    /// Number of delays handed out since the last reset
+    pub fn attempt(&self) -> u32 {
+        self.attempt
+    }
+
+    /// Restart the sequence after a successful operation
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+    }
+
+    /// Next jittered delay; advances the sequence
+    pub fn next_delay<RNG>(&mut self, rng: &mut RNG) -> Duration
+    where
+        RNG: rand_core::RngCore,
+    {
+        let ceiling = backoff_ceiling_ms(&self.config, self.attempt);
+        self.attempt = self.attempt.saturating_add(1);
+        Duration::from_millis(apply_jitter(ceiling, rng.next_u32()))
+    }
+}
+
+/// Un-jittered delay ceiling for a given attempt number
+///
+/// Returns `initial_ms * 2^attempt`, clamped to `max_ms`.
+pub fn backoff_ceiling_ms(config: &BackoffConfig, attempt: u32) -> u64 {
+    let factor = 1u64.checked_shl(attempt).unwrap_or(u64::MAX);
+    config.initial_ms.saturating_mul(factor).min(config.max_ms)
+}
+
+/// Apply "equal jitter" to a delay ceiling
+///
+/// Result is uniformly spread over `[ceiling / 2, ceiling]`, so the delay
+/// still grows with each attempt but never collapses to zero.
+fn apply_jitter(ceiling_ms: u64, random: u32) -> u64 {
+    let half = ceiling_ms / 2;
+    let span = ceiling_ms - half;
+    half + (random as u64) % (span + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: BackoffConfig = BackoffConfig {
+        initial_ms: 1_000,
+        max_ms: 60_000,
+    };
+
+    #[test]
+    fn test_ceiling_doubles_until_cap() {
+        assert_eq!(backoff_ceiling_ms(&CONFIG, 0), 1_000);
+        assert_eq!(backoff_ceiling_ms(&CONFIG, 1), 2_000);
+        assert_eq!(backoff_ceiling_ms(&CONFIG, 5), 32_000);
+        assert_eq!(backoff_ceiling_ms(&CONFIG, 6), 60_000);
+        assert_eq!(backoff_ceiling_ms(&CONFIG, 200), 60_000);
+    }
+
+    #[test]
+    fn test_jitter_bounds() {
+        assert_eq!(apply_jitter(1_000, 0), 500);
+        assert_eq!(apply_jitter(1_000, 500), 1_000);
+        assert!(apply_jitter(1_000, u32::MAX) <= 1_000);
+        assert_eq!(apply_jitter(0, u32::MAX), 0);
+    }
+
+    #[test]
+    fn test_reset_restarts_sequence() {
+        let mut backoff = Backoff::new(CONFIG);
+        backoff.attempt = 7;
+        backoff.reset();
+        assert_eq!(backoff.attempt(), 0);
+    }
+}
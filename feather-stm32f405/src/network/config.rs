@@ -5,14 +5,30 @@
 /// SNTP client configuration
 #[derive(Debug, Clone)]
 pub struct SntpConfig {
-    /// NTP servers to try (in order)
+    /// NTP servers to sample (up to `sntp::MAX_SERVERS`)
+    ///
+    /// List at least three independent servers so a single wrong one is
+    /// outvoted.
     pub servers: &'static [&'static str],
     /// Request timeout in milliseconds
     pub timeout_ms: u64,
-    /// Number of retry attempts per server
+    /// Failed queries tolerated per server before it is skipped
     pub retry_count: usize,
+    /// Samples collected per server; the lowest-delay one is used
+    pub samples_per_server: usize,
     /// Maximum accepted stratum level (1-15)
     pub max_stratum: u8,
+    /// Longest interval between resyncs in milliseconds
+    ///
+    /// Resyncs start at `min_poll_interval_ms` and double towards this
+    /// while the measured offset stays below `max_offset_us`.
+    pub poll_interval_ms: u64,
+    /// Shortest interval between resyncs in milliseconds
+    pub min_poll_interval_ms: u64,
+    /// Offset at a resync above which the poll interval is halved
+    pub max_offset_us: u64,
+    /// Retry back-off when every server fails
+    pub backoff: BackoffConfig,
 }
 
 impl Default for SntpConfig {
@@ -21,7 +37,46 @@ impl Default for SntpConfig {
             servers: &["pool.ntp.org", "time.google.com", "time.cloudflare.com"],
             timeout_ms: 5000,
             retry_count: 3,
+            samples_per_server: 4,
             max_stratum: 3,
+            poll_interval_ms: 900_000,
+            min_poll_interval_ms: 64_000,
+            max_offset_us: 50_000,
+            backoff: BackoffConfig {
+                initial_ms: 30_000,
+                max_ms: 900_000,
+            },
+        }
+    }
+}
+
+/// TCP socket timeouts and keep-alive
+#[derive(Debug, Clone, Copy)]
+pub struct TcpConfig {
+    /// Deadline for the TCP three-way handshake in milliseconds
+    pub connect_timeout_ms: u64,
+    /// Deadline for a single read in milliseconds (`None` = wait forever)
+    ///
+    /// Leave unset for long-lived sessions whose protocol has its own
+    /// keep-alive (MQTT), otherwise an idle connection is dropped.
+    pub read_timeout_ms: Option<u64>,
+    /// Deadline for a single write or flush in milliseconds (`None` = wait
+    /// forever)
+    pub write_timeout_ms: Option<u64>,
+    /// Interval between TCP keep-alive probes in milliseconds (`None` = off)
+    ///
+    /// The connection is aborted when the peer stays silent for three
+    /// intervals.
+    pub keep_alive_ms: Option<u64>,
+}
+
+impl Default for TcpConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout_ms: 10_000,
+            read_timeout_ms: None,
+            write_timeout_ms: Some(10_000),
+            keep_alive_ms: Some(30_000),
         }
     }
 }
@@ -45,3 +100,21 @@ impl Default for NetworkConfig {
         }
     }
 }
+
+/// Exponential back-off configuration for reconnect and retry loops
+#[derive(Debug, Clone, Copy)]
+pub struct BackoffConfig {
+    /// Delay ceiling for the first retry in milliseconds
+    pub initial_ms: u64,
+    /// Cap on any single delay in milliseconds
+    pub max_ms: u64,
+}
+
+impl Default for BackoffConfig {
+    fn default() -> Self {
+        Self {
+            initial_ms: 1_000,
+            max_ms: 60_000,
+        }
+    }
+}
@@ -0,0 +1,649 @@
+#![deny(unsafe_code)]
+#![deny(warnings)]
+//! Secure transport connector: DNS → TCP → TLS 1.3
+//!
+//! `SecureConnector` is the single path every TLS-based protocol uses to reach
+//! a server. It resolves the host, opens a TCP connection on caller-provided
+//! socket buffers, performs the TLS 1.3 handshake with the hardware RNG and
+//! returns an owned `TlsStream`. The stream implements `embedded_io_async`
+//! `Read + Write`, so protocol clients (MQTT today, HTTPS later) only deal
+//! with an established byte stream.
+//!
+//! # Architecture
+//!
+//! ```text
+//! MqttClient ──┐
+//! TlsClient  ──┼──► SecureConnector::connect ──► TlsStream (Read + Write)
+//! (HTTPS)    ──┘        DNS, TCP, TLS             owns socket + TLS state
+//! ```
+//!
+//! # Example
+//!
+//! ```no_run
+//! let connector = SecureConnector::new(TlsClientConfig {
+//!     server_name: "broker.example.com",
+//!     server_port: 8883,
+//!     ..Default::default()
+//! });
+//! let mut stream = connector.connect(stack, &mut rng, &mut rx_buf, &mut tx_buf).await?;
+//! stream.write_all(b"hello").await?;
+//! stream.close().await?;
+//! ```
+
+use core::marker::PhantomData;
+
+use defmt::{debug, error, info, warn, Debug2Format};
+use embassy_net::{IpAddress, IpEndpoint, Stack};
+use embassy_time::{with_timeout, Duration, Instant};
+use embedded_io_async::{ErrorKind, ErrorType, Read, Write};
+use embedded_tls::alert::AlertDescription;
+use embedded_tls::{
+    Aes128GcmSha256, Aes256GcmSha384, Certificate, CryptoProvider, MaxFragmentLength,
+    SignatureScheme, TlsCipherSuite, TlsConfig, TlsConnection, TlsContext, TlsVerifier,
+};
+use p256::ecdsa::{signature::SignerMut, DerSignature, SigningKey};
+
+use crate::tls_buffers::{self, TlsBuffersToken, MAX_PLAINTEXT_LEN};
+
+use super::dns;
+use super::error::{NetworkError, TlsAlert, TlsError};
+use super::socket::AsyncTcpSocket;
+use super::tls::{CipherSuite, FragmentLength, TlsClientConfig};
+use super::verifier::{ServerVerifier, VerificationOutcome, VerifyError};
+
+/// Crypto provider that wraps an RNG, the server verifier and the optional
+/// client identity for the cipher suite `CS`
+struct SimpleCryptoProvider<'a, RNG, CS> {
+    rng: &'a mut RNG,
+    verifier: ServerVerifier<'a>,
+    client_certificate: Option<&'static [u8]>,
+    signing_key: Option<SigningKey>,
+    _cipher_suite: PhantomData<CS>,
+}
+
+impl<'a, RNG, CS> SimpleCryptoProvider<'a, RNG, CS> {
+    fn new(rng: &'a mut RNG, verifier: ServerVerifier<'a>) -> Self {
+        Self {
+            rng,
+            verifier,
+            client_certificate: None,
+            signing_key: None,
+            _cipher_suite: PhantomData,
+        }
+    }
+
+    /// Present the certificate and sign CertificateVerify with the key, if given
+    fn with_client_identity(mut self, client: Option<(&'static [u8], SigningKey)>) -> Self {
+        if let Some((certificate, key)) = client {
+            self.client_certificate = Some(certificate);
+            self.signing_key = Some(key);
+        }
+        self
+    }
+}
+
+impl<'a, RNG, CS> CryptoProvider for SimpleCryptoProvider<'a, RNG, CS>
+where
+    RNG: rand_core::CryptoRngCore,
+    CS: TlsCipherSuite,
+{
+    type CipherSuite = CS;
+    type Signature = DerSignature;
+
+    fn rng(&mut self) -> impl rand_core::CryptoRngCore {
+        &mut *self.rng
+    }
+
+    fn verifier(
+        &mut self,
+    ) -> Result<&mut impl TlsVerifier<Self::CipherSuite>, embedded_tls::TlsError> {
+        Ok(&mut self.verifier)
+    }
+
+    fn signer(
+        &mut self,
+        _key_der: &[u8],
+    ) -> Result<(impl SignerMut<Self::Signature>, SignatureScheme), embedded_tls::TlsError> {
+        // The key was parsed and matched against the certificate before the
+        // handshake, so the DER copy in `TlsConfig` is not needed here
+        let key = self
+            .signing_key
+            .clone()
+            .ok_or(embedded_tls::TlsError::InvalidCertificate)?;
+        Ok((key, SignatureScheme::EcdsaSecp256r1Sha256))
+    }
+
+    fn client_cert(&mut self) -> Option<Certificate<impl AsRef<[u8]>>> {
+        self.client_certificate.map(Certificate::X509)
+    }
+}
+
+/// Establishes TLS 1.3 connections to a configured server
+pub struct SecureConnector {
+    config: TlsClientConfig,
+}
+
+impl SecureConnector {
+    /// Create a connector for the given server and TLS settings
+    pub fn new(config: TlsClientConfig) -> Self {
+        Self { config }
+    }
+
+    /// Configuration this connector connects with
+    pub fn config(&self) -> &TlsClientConfig {
+        &self.config
+    }
+
+    /// Resolve, connect and perform the TLS 1.3 handshake
+    ///
+    /// # Arguments
+    ///
+    /// * `stack` - Embassy network stack for DNS and TCP operations
+    /// * `rng` - Hardware random number generator (STM32F405 RNG peripheral)
+    /// * `tcp_rx` - TCP receive buffer, borrowed for the stream lifetime
+    /// * `tcp_tx` - TCP transmit buffer, borrowed for the stream lifetime
+    ///
+    /// # Returns
+    ///
+    /// Returns an established `TlsStream`, or a `NetworkError` if DNS, TCP or
+    /// the handshake fails. Every resolved address is tried before a TCP
+    /// failure is reported (the error of the last one). A server rejected by
+    /// certificate verification is reported as `TlsError::CertificateError`
+    /// (`TlsError::PinMismatch` if no pin matched); an unusable client
+    /// certificate, key or PSK as `TlsError::ClientIdentityError`. A server
+    /// ignoring the requested Max Fragment Length is reported as
+    /// `TlsError::FragmentLengthRefused`. Alerts from the server, malformed
+    /// messages and unsupported cipher suites keep their cause (see
+    /// `tls_error`); transport failures during the handshake stay network
+    /// errors such as `NetworkError::Timeout` or `NetworkError::ConnectionReset`.
+    pub async fn connect<'a, RNG>(
+        &self,
+        stack: &Stack<'static>,
+        rng: &mut RNG,
+        tcp_rx: &'a mut [u8],
+        tcp_tx: &'a mut [u8],
+    ) -> Result<TlsStream<'a>, NetworkError>
+    where
+        RNG: rand_core::RngCore + rand_core::CryptoRng,
+    {
+        let host = self.config.server_name;
+        let auth_mode = self.config.auth_mode();
+
+        // Records must fit the static buffers
+        let fragment_len = self
+            .config
+            .max_fragment_length
+            .map_or(MAX_PLAINTEXT_LEN, FragmentLength::bytes);
+        if fragment_len > tls_buffers::FRAGMENT_LEN {
+            error!(
+                "{}-byte records do not fit the {}-byte TLS buffers",
+                fragment_len,
+                tls_buffers::FRAGMENT_LEN
+            );
+            return Err(TlsError::BuffersTooSmall.into());
+        }
+
+        // Validate credentials before touching the network
+        let client_key = match self.config.psk {
+            Some(psk) => {
+                psk.validate()?;
+                if self.config.client_identity.is_some() || self.config.verify_server {
+                    warn!("PSK configured: certificate settings are ignored");
+                }
+                None
+            }
+            None => {
+                if self.config.verify_server
+                    && self.config.ca_certificates.is_empty()
+                    && self.config.pins.is_empty()
+                {
+                    error!("Certificate verification requested without CA bundle or pins");
+                    return Err(TlsError::CertificateError.into());
+                }
+                self.config
+                    .client_identity
+                    .map(|identity| identity.signing_key())
+                    .transpose()?
+            }
+        };
+
+        // Step 1: DNS resolution (every answer; IPv6 first with the `ipv6`
+        // feature)
+        let addresses = dns::resolve(stack, host).await?;
+        info!("Resolved {} to {} address(es)", host, addresses.len());
+
+        // Step 2: Create and connect TCP socket to the first address that
+        // accepts, retrying all of them on the fallback port (e.g. 443 when
+        // 8883 is blocked) if one is configured
+        let mut socket = AsyncTcpSocket::new(*stack, tcp_rx, tcp_tx, self.config.tcp);
+        let port = self.config.server_port;
+        let endpoint = match (
+            connect_any(&mut socket, &addresses, port).await,
+            self.config.fallback_port,
+        ) {
+            (Ok(endpoint), _) => endpoint,
+            (Err(e), Some(fallback)) => {
+                warn!(
+                    "TCP connect to port {} failed ({:?}), trying port {}",
+                    port, e, fallback
+                );
+                connect_any(&mut socket, &addresses, fallback).await?
+            }
+            (Err(e), None) => return Err(e),
+        };
+        info!("TCP connection established to {}", Debug2Format(&endpoint));
+
+        // Step 3: Claim the TLS buffers in main SRAM (fails if another
+        // connection still holds them)
+        let buffers = tls_buffers::acquire().map_err(|e| {
+            error!("TLS buffers unavailable: {:?}", e);
+            e
+        })?;
+        // SAFETY: the token ends up in `TlsStream::_buffers`, declared after
+        // the connection that borrows the slices, so it is dropped last. On
+        // every early return below the slices are unused once it drops.
+        #[allow(unsafe_code)]
+        let (read_buf, write_buf, buffers) = unsafe { buffers.into_parts() };
+
+        debug!(
+            "TLS buffers allocated: read={} bytes, write={} bytes (main SRAM)",
+            read_buf.len(),
+            write_buf.len()
+        );
+
+        // Step 4: Configure TLS with server name for SNI, then either the PSK
+        // or server verification and the optional client identity
+        let mut tls_config = TlsConfig::new().with_server_name(host);
+
+        let (trust_anchors, pins) = match self.config.psk {
+            Some(psk) => {
+                // The server authenticates by knowing the key; no certificate
+                // is exchanged
+                tls_config = tls_config.with_psk(psk.key, &[psk.identity]);
+                (&[][..], &[][..])
+            }
+            None if self.config.verify_server => (self.config.ca_certificates, self.config.pins),
+            None => {
+                warn!("Server certificate verification disabled");
+                (&[][..], &[][..])
+            }
+        };
+        let outcome = VerificationOutcome::new(None);
+        let verifier = ServerVerifier::new(host, trust_anchors, pins, &outcome);
+
+        let alpn_protocols = self.config.alpn_protocols;
+        if !alpn_protocols.is_empty() {
+            debug!("Offering {} ALPN protocol(s)", alpn_protocols.len());
+            tls_config = tls_config.with_alpn_protocols(alpn_protocols);
+        }
+
+        if let Some(length) = self.config.max_fragment_length {
+            debug!("Requesting max fragment length {} bytes", length.bytes());
+            tls_config = tls_config.with_max_fragment_length(match length {
+                FragmentLength::Bytes512 => MaxFragmentLength::Bits9,
+                FragmentLength::Bytes1024 => MaxFragmentLength::Bits10,
+                FragmentLength::Bytes2048 => MaxFragmentLength::Bits11,
+                FragmentLength::Bytes4096 => MaxFragmentLength::Bits12,
+            });
+        }
+
+        let client = match (self.config.client_identity, client_key) {
+            (Some(identity), Some(key)) => {
+                info!("Presenting client certificate for mutual TLS");
+                tls_config = tls_config.with_priv_key(identity.private_key);
+                Some((identity.certificate, key))
+            }
+            _ => None,
+        };
+
+        // Step 5: Perform TLS handshake with the configured cipher suite
+        let cipher_suite = self.config.cipher_suite;
+        info!(
+            "Initiating TLS 1.3 handshake ({:?}, {:?}) with hardware RNG...",
+            auth_mode, cipher_suite
+        );
+        let started = Instant::now();
+        let fragment_limited = self.config.max_fragment_length.is_some();
+        let timeout = Duration::from_millis(self.config.handshake_timeout_ms);
+        let connection = match cipher_suite {
+            CipherSuite::Aes128GcmSha256 => {
+                let provider =
+                    SimpleCryptoProvider::new(rng, verifier).with_client_identity(client);
+                Connection::Aes128GcmSha256(
+                    open(socket, read_buf, write_buf, &tls_config, provider, timeout)
+                        .await
+                        .map_err(|e| handshake_error(e, fragment_limited, outcome.get()))?,
+                )
+            }
+            CipherSuite::Aes256GcmSha384 => {
+                let provider =
+                    SimpleCryptoProvider::new(rng, verifier).with_client_identity(client);
+                Connection::Aes256GcmSha384(
+                    open(socket, read_buf, write_buf, &tls_config, provider, timeout)
+                        .await
+                        .map_err(|e| handshake_error(e, fragment_limited, outcome.get()))?,
+                )
+            }
+        };
+
+        info!(
+            "TLS 1.3 handshake with {} completed in {} ms ({:?}, {:?})",
+            host,
+            started.elapsed().as_millis(),
+            auth_mode,
+            cipher_suite
+        );
+        Ok(TlsStream {
+            connection,
+            _buffers: buffers,
+            offered_alpn: alpn_protocols,
+            remote_port: endpoint.port,
+        })
+    }
+}
+
+/// Connect `socket` to the first of `addresses` that accepts on `port`
+///
+/// Returns the error of the last candidate once every address has failed.
+async fn connect_any(
+    socket: &mut AsyncTcpSocket<'_>,
+    addresses: &[IpAddress],
+    port: u16,
+) -> Result<IpEndpoint, NetworkError> {
+    let mut last_error = NetworkError::DnsError;
+    for &address in addresses {
+        let endpoint = IpEndpoint::new(address, port);
+        match socket.connect(endpoint).await {
+            Ok(()) => return Ok(endpoint),
+            Err(e) => {
+                warn!("TCP connect to {} failed: {:?}", Debug2Format(&endpoint), e);
+                socket.close();
+                last_error = e;
+            }
+        }
+    }
+    Err(last_error)
+}
+
+/// Run the handshake for one cipher suite, giving up after `timeout`
+///
+/// An expired deadline is reported like a socket timeout, which `tls_error`
+/// maps to `NetworkError::Timeout`.
+async fn open<'a, RNG, CS>(
+    socket: AsyncTcpSocket<'a>,
+    read_buf: &'a mut [u8],
+    write_buf: &'a mut [u8],
+    tls_config: &TlsConfig<'_>,
+    provider: SimpleCryptoProvider<'_, RNG, CS>,
+    timeout: Duration,
+) -> Result<TlsConnection<'a, AsyncTcpSocket<'a>, CS>, embedded_tls::TlsError>
+where
+    RNG: rand_core::CryptoRngCore,
+    CS: TlsCipherSuite,
+{
+    let mut connection = TlsConnection::new(socket, read_buf, write_buf);
+    with_timeout(
+        timeout,
+        connection.open(TlsContext::new(tls_config, provider)),
+    )
+    .await
+    .map_err(|_| embedded_tls::TlsError::Io(ErrorKind::TimedOut))??;
+    Ok(connection)
+}
+
+/// Map a handshake error to `NetworkError`
+///
+/// `rejected` is the reason recorded by `ServerVerifier`, which embedded-tls
+/// reduces to `InvalidCertificate`. With Max Fragment Length requested, a
+/// record that overflows the buffers means the server ignored the extension
+/// (RFC 6066 lets it do so silently). Everything else maps as in `tls_error`.
+fn handshake_error(
+    e: embedded_tls::TlsError,
+    fragment_limited: bool,
+    rejected: Option<VerifyError>,
+) -> NetworkError {
+    let mapped = match e {
+        embedded_tls::TlsError::InvalidCertificate | embedded_tls::TlsError::InvalidSignature => {
+            match rejected {
+                Some(VerifyError::PinMismatch) => TlsError::PinMismatch.into(),
+                _ => TlsError::CertificateError.into(),
+            }
+        }
+        embedded_tls::TlsError::InsufficientSpace if fragment_limited => {
+            error!("Server does not honour max_fragment_length");
+            TlsError::FragmentLengthRefused.into()
+        }
+        other => tls_error(other, TlsError::HandshakeFailed),
+    };
+    error!("TLS handshake failed: {} ({:?})", mapped, Debug2Format(&e));
+    mapped
+}
+
+/// Translate an embedded-tls error into `NetworkError` (SR-ERR-001)
+///
+/// Transport failures stay network errors, so logs and telemetry can tell
+/// an unreachable broker from one that rejects the session. Errors without
+/// a more precise variant become `fallback`.
+fn tls_error(e: embedded_tls::TlsError, fallback: TlsError) -> NetworkError {
+    use embedded_tls::TlsError as E;
+
+    match e {
+        E::Io(kind) => kind.into(),
+        E::ConnectionClosed => TlsError::ConnectionClosed.into(),
+        E::HandshakeAborted(_, description) => TlsError::AlertReceived(alert(description)).into(),
+        E::InvalidCertificate | E::InvalidCertificateEntry | E::InvalidSignature => {
+            TlsError::CertificateError.into()
+        }
+        E::InvalidCipherSuite | E::InvalidSignatureScheme => {
+            TlsError::UnsupportedCipherSuite.into()
+        }
+        E::DecodeError
+        | E::ParseError(_)
+        | E::InvalidRecord
+        | E::UnknownContentType
+        | E::InvalidHandshake
+        | E::InvalidExtensionsLength
+        | E::InvalidSupportedVersions
+        | E::InvalidKeyShare => TlsError::DecodeError.into(),
+        _ => fallback.into(),
+    }
+}
+
+/// Reduce an alert description from the peer to `TlsAlert`
+fn alert(description: AlertDescription) -> TlsAlert {
+    match description {
+        AlertDescription::HandshakeFailure => TlsAlert::HandshakeFailure,
+        AlertDescription::BadCertificate => TlsAlert::BadCertificate,
+        AlertDescription::UnsupportedCertificate => TlsAlert::UnsupportedCertificate,
+        AlertDescription::CertificateRevoked => TlsAlert::CertificateRevoked,
+        AlertDescription::CertificateExpired => TlsAlert::CertificateExpired,
+        AlertDescription::CertificateUnknown => TlsAlert::CertificateUnknown,
+        AlertDescription::UnknownCa => TlsAlert::UnknownCa,
+        AlertDescription::AccessDenied => TlsAlert::AccessDenied,
+        AlertDescription::DecodeError => TlsAlert::DecodeError,
+        AlertDescription::DecryptError => TlsAlert::DecryptError,
+        AlertDescription::ProtocolVersion => TlsAlert::ProtocolVersion,
+        AlertDescription::InsufficientSecurity => TlsAlert::InsufficientSecurity,
+        AlertDescription::IllegalParameter => TlsAlert::IllegalParameter,
+        AlertDescription::UnrecognizedName => TlsAlert::UnrecognizedName,
+        AlertDescription::UnknownPskIdentity => TlsAlert::UnknownPskIdentity,
+        AlertDescription::CertificateRequired => TlsAlert::CertificateRequired,
+        AlertDescription::NoApplicationProtocol => TlsAlert::NoApplicationProtocol,
+        AlertDescription::InternalError => TlsAlert::InternalError,
+        _ => TlsAlert::Other,
+    }
+}
+
+/// TLS connection for the negotiated cipher suite
+///
+/// The suite is a type parameter in embedded-tls, so a runtime choice needs
+/// one variant per supported suite.
+enum Connection<'a> {
+    Aes128GcmSha256(TlsConnection<'a, AsyncTcpSocket<'a>, Aes128GcmSha256>),
+    Aes256GcmSha384(TlsConnection<'a, AsyncTcpSocket<'a>, Aes256GcmSha384>),
+}
+
+/// Established TLS 1.3 stream
+///
+/// Owns the TCP socket, the TLS record state and the claim on the static TLS
+/// buffers. Dropping the stream closes the TCP connection without a TLS
+/// `close_notify` and releases the buffers; call `close` for an orderly
+/// shutdown.
+pub struct TlsStream<'a> {
+    // Field order matters: the connection borrows the TLS buffers and must be
+    // dropped before the token that releases them.
+    connection: Connection<'a>,
+    _buffers: TlsBuffersToken,
+    offered_alpn: &'static [&'static [u8]],
+    remote_port: u16,
+}
+
+impl TlsStream<'_> {
+    /// Cipher suite of this connection
+    pub fn cipher_suite(&self) -> CipherSuite {
+        match self.connection {
+            Connection::Aes128GcmSha256(_) => CipherSuite::Aes128GcmSha256,
+            Connection::Aes256GcmSha384(_) => CipherSuite::Aes256GcmSha384,
+        }
+    }
+
+    /// ALPN protocols offered in the ClientHello
+    ///
+    /// Only what the client offered: embedded-tls does not report the
+    /// server's selection, and a server that ignores ALPN completes the
+    /// handshake without choosing any of them.
+    pub fn offered_alpn(&self) -> &'static [&'static [u8]] {
+        self.offered_alpn
+    }
+
+    /// Server port the connection was established on (after any fallback)
+    pub fn remote_port(&self) -> u16 {
+        self.remote_port
+    }
+
+    /// Send `close_notify` and close the TCP connection
+    pub async fn close(self) -> Result<(), NetworkError> {
+        let Self {
+            connection,
+            _buffers: buffers,
+            ..
+        } = self;
+
+        let result = match connection {
+            Connection::Aes128GcmSha256(c) => c.close().await.map_err(|(_socket, e)| e),
+            Connection::Aes256GcmSha384(c) => c.close().await.map_err(|(_socket, e)| e),
+        }
+        .map_err(|e| {
+            warn!("TLS close returned error: {:?}", Debug2Format(&e));
+            tls_error(e, TlsError::ConnectionClosed)
+        });
+        drop(buffers);
+        result?;
+        Ok(())
+    }
+}
+
+/// Map a record-layer error to `NetworkError`
+///
+/// After any error the TLS session cannot be used further; errors without a
+/// more precise cause are reported as a closed connection.
+fn stream_error(e: embedded_tls::TlsError) -> NetworkError {
+    debug!("TLS stream error: {:?}", Debug2Format(&e));
+    tls_error(e, TlsError::ConnectionClosed)
+}
+
+impl ErrorType for TlsStream<'_> {
+    type Error = NetworkError;
+}
+
+impl Read for TlsStream<'_> {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        match &mut self.connection {
+            Connection::Aes128GcmSha256(c) => c.read(buf).await,
+            Connection::Aes256GcmSha384(c) => c.read(buf).await,
+        }
+        .map_err(stream_error)
+    }
+}
+
+impl Write for TlsStream<'_> {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        match &mut self.connection {
+            Connection::Aes128GcmSha256(c) => c.write(buf).await,
+            Connection::Aes256GcmSha384(c) => c.write(buf).await,
+        }
+        .map_err(stream_error)
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        match &mut self.connection {
+            Connection::Aes128GcmSha256(c) => c.flush().await,
+            Connection::Aes256GcmSha384(c) => c.flush().await,
+        }
+        .map_err(stream_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_tls::alert::AlertLevel;
+    use embedded_tls::TlsError as E;
+
+    #[test]
+    fn test_transport_errors_stay_network_errors() {
+        assert!(matches!(
+            tls_error(E::Io(ErrorKind::TimedOut), TlsError::HandshakeFailed),
+            NetworkError::Timeout
+        ));
+        assert!(matches!(
+            tls_error(E::Io(ErrorKind::BrokenPipe), TlsError::HandshakeFailed),
+            NetworkError::SocketError
+        ));
+        assert!(matches!(
+            tls_error(E::Io(ErrorKind::ConnectionReset), TlsError::HandshakeFailed),
+            NetworkError::ConnectionReset
+        ));
+    }
+
+    #[test]
+    fn test_alert_description_is_preserved() {
+        let e = E::HandshakeAborted(AlertLevel::Fatal, AlertDescription::UnknownCa);
+        assert!(matches!(
+            handshake_error(e, false, None),
+            NetworkError::Tls(TlsError::AlertReceived(TlsAlert::UnknownCa))
+        ));
+    }
+
+    #[test]
+    fn test_handshake_failure_causes() {
+        assert!(matches!(
+            handshake_error(E::InvalidCipherSuite, false, None),
+            NetworkError::Tls(TlsError::UnsupportedCipherSuite)
+        ));
+        assert!(matches!(
+            handshake_error(E::DecodeError, false, None),
+            NetworkError::Tls(TlsError::DecodeError)
+        ));
+        assert!(matches!(
+            handshake_error(E::InvalidCertificate, false, Some(VerifyError::PinMismatch)),
+            NetworkError::Tls(TlsError::PinMismatch)
+        ));
+        assert!(matches!(
+            handshake_error(E::InsufficientSpace, true, None),
+            NetworkError::Tls(TlsError::FragmentLengthRefused)
+        ));
+        assert!(matches!(
+            handshake_error(E::InternalError, false, None),
+            NetworkError::Tls(TlsError::HandshakeFailed)
+        ));
+    }
+
+    #[test]
+    fn test_stream_errors_default_to_closed() {
+        assert!(matches!(
+            stream_error(E::InternalError),
+            NetworkError::Tls(TlsError::ConnectionClosed)
+        ));
+    }
+}
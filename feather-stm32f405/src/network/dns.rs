@@ -0,0 +1,123 @@
+#![deny(unsafe_code)]
+#![deny(warnings)]
+//! Host name resolution returning every address
+//!
+//! Brokers behind round-robin DNS or load balancers publish several
+//! addresses, and any one of them may be down. `resolve` returns all answers
+//! so callers can try each in turn instead of giving up on the first.
+//!
+//! smoltcp keeps only `DNS_MAX_RESULT_COUNT` answers per query, 1 unless a
+//! `dns-max-result-count-N` feature is enabled; Cargo.toml selects 8 to match
+//! `MAX_ADDRESSES`.
+//!
+//! # IPv6 (Happy Eyeballs lite)
+//!
+//! With the `ipv6` Cargo feature, AAAA records are queried as well whenever
+//! the stack has an IPv6 configuration. The two families are interleaved,
+//! IPv6 first (RFC 8305 §4), so a broken IPv6 path costs one connect timeout
+//! before IPv4 is tried. Unlike full Happy Eyeballs the attempts run one
+//! after another: the firmware has buffers for a single TCP connection.
+//!
+//! embassy-net has no SLAAC, so IPv6 needs a static configuration; with
+//! DHCPv4 only, just A records are queried.
+
+use defmt::{debug, error, Debug2Format};
+use embassy_net::dns::DnsQueryType;
+use embassy_net::{IpAddress, Stack};
+use heapless::Vec;
+
+use super::error::NetworkError;
+
+/// Maximum number of addresses kept per host (both families)
+///
+/// Keep in step with the smoltcp `dns-max-result-count-N` feature.
+pub const MAX_ADDRESSES: usize = 8;
+
+/// Resolved addresses in the order they should be tried
+pub type Addresses = Vec<IpAddress, MAX_ADDRESSES>;
+
+/// Resolve `host` to every A (and, with IPv6, AAAA) record
+///
+/// # Returns
+///
+/// The addresses in connection order, never empty.
+///
+/// # Errors
+///
+/// Returns `NetworkError::DnsError` if no query produced an address.
+pub async fn resolve(stack: &Stack<'static>, host: &str) -> Result<Addresses, NetworkError> {
+    let ipv4 = query(stack, host, DnsQueryType::A).await;
+
+    #[cfg(feature = "ipv6")]
+    let ipv6 = if stack.config_v6().is_some() {
+        query(stack, host, DnsQueryType::Aaaa).await
+    } else {
+        Vec::new()
+    };
+    #[cfg(not(feature = "ipv6"))]
+    let ipv6: Addresses = Vec::new();
+
+    let addresses: Addresses = interleave(&ipv6, &ipv4);
+    if addresses.is_empty() {
+        error!("DNS returned no results for {}", host);
+        return Err(NetworkError::DnsError);
+    }
+    Ok(addresses)
+}
+
+/// Run one DNS query; a failed query counts as no answers
+async fn query(stack: &Stack<'static>, host: &str, kind: DnsQueryType) -> Addresses {
+    match stack.dns_query(host, kind).await {
+        Ok(answers) => answers.iter().copied().take(MAX_ADDRESSES).collect(),
+        Err(e) => {
+            debug!(
+                "DNS {:?} query for {} failed: {:?}",
+                Debug2Format(&kind),
+                host,
+                Debug2Format(&e)
+            );
+            Vec::new()
+        }
+    }
+}
+
+/// Alternate between `preferred` and `other`, starting with `preferred`
+///
+/// Entries beyond the capacity `N` are dropped.
+pub fn interleave<T: Copy, const N: usize>(preferred: &[T], other: &[T]) -> Vec<T, N> {
+    let mut result = Vec::new();
+    let longest = preferred.len().max(other.len());
+    for index in 0..longest {
+        for list in [preferred, other] {
+            if let Some(&item) = list.get(index) {
+                if result.push(item).is_err() {
+                    return result;
+                }
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interleave_alternates_families() {
+        let result: Vec<u8, 8> = interleave(&[6, 66, 166], &[4, 44]);
+        assert_eq!(result.as_slice(), &[6, 4, 66, 44, 166]);
+    }
+
+    #[test]
+    fn test_interleave_single_family() {
+        let result: Vec<u8, 8> = interleave(&[], &[4, 44]);
+        assert_eq!(result.as_slice(), &[4, 44]);
+    }
+
+    #[test]
+    fn test_interleave_is_bounded() {
+        let result: Vec<u8, 3> = interleave(&[1, 2, 3], &[4, 5, 6]);
+        assert_eq!(result.as_slice(), &[1, 4, 2]);
+    }
+}
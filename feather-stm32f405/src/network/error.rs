@@ -25,6 +25,14 @@ pub enum NetworkError {
     DnsError,
     /// Socket bind/connect error
     SocketError,
+    /// Server actively refused the TCP connection (RST in reply to SYN)
+    ConnectionRefused,
+    /// Established TCP connection reset by the peer
+    ConnectionReset,
+    /// No route to the server (no gateway or interface for its address)
+    NoRoute,
+    /// Peer closed the connection (FIN) while more data was expected
+    Eof,
     /// Request timeout
     Timeout,
     /// Invalid response from server
@@ -53,10 +61,72 @@ pub enum TlsError {
     HandshakeFailed,
     /// Certificate verification error
     CertificateError,
-    /// TLS alert received from peer
-    AlertReceived,
+    /// Server certificate matches none of the configured pins
+    PinMismatch,
+    /// Configured record size does not fit the TLS buffers
+    BuffersTooSmall,
+    /// Server ignored Max Fragment Length and sent a larger record
+    FragmentLengthRefused,
+    /// Client certificate, private key or PSK unusable (or key and
+    /// certificate do not match)
+    ClientIdentityError,
+    /// Peer aborted the session with a TLS alert
+    AlertReceived(TlsAlert),
+    /// Malformed or unexpected TLS message from the peer
+    DecodeError,
+    /// Server chose a cipher suite or signature scheme this client does not
+    /// support
+    UnsupportedCipherSuite,
     /// Connection closed unexpectedly
     ConnectionClosed,
+    /// TLS buffers are held by another connection
+    BuffersInUse,
+}
+
+/// Alert description sent by the TLS peer (RFC 8446 §6.2)
+///
+/// Lists the alerts that point at a configuration problem; anything else is
+/// reported as `Other`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+pub enum TlsAlert {
+    /// No acceptable set of security parameters
+    HandshakeFailure,
+    /// Client certificate corrupt or its signature did not verify
+    BadCertificate,
+    /// Client certificate of an unsupported type
+    UnsupportedCertificate,
+    /// Client certificate revoked by its signer
+    CertificateRevoked,
+    /// Client certificate expired or not yet valid
+    CertificateExpired,
+    /// Client certificate rejected for another reason
+    CertificateUnknown,
+    /// Client certificate issuer not trusted by the server
+    UnknownCa,
+    /// Valid credentials, but access refused by the server's policy
+    AccessDenied,
+    /// Server could not parse a client message
+    DecodeError,
+    /// Handshake cryptographic operation failed (e.g. Finished)
+    DecryptError,
+    /// Server does not speak TLS 1.3
+    ProtocolVersion,
+    /// Server requires parameters stronger than offered
+    InsufficientSecurity,
+    /// Field value out of range or inconsistent
+    IllegalParameter,
+    /// Server has no certificate for the requested SNI host name
+    UnrecognizedName,
+    /// Server does not know the offered PSK identity
+    UnknownPskIdentity,
+    /// Server requires a client certificate
+    CertificateRequired,
+    /// Server supports none of the offered ALPN protocols
+    NoApplicationProtocol,
+    /// Server-side failure unrelated to the client
+    InternalError,
+    /// Any other alert
+    Other,
 }
 
 /// MQTT operation errors
@@ -74,13 +144,40 @@ pub enum MqttError {
 }
 
 /// SNTP operation errors
-#[derive(Debug, Clone, Copy, Format)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
 #[allow(dead_code)]
 pub enum SntpError {
     /// Invalid stratum received
     InvalidStratum,
     /// Parse error
     ParseError,
+    /// Packet is not a server reply (mode other than 4)
+    InvalidMode,
+    /// Reply uses an NTP version other than 3 or 4
+    UnsupportedVersion,
+    /// Origin timestamp does not echo our request (stale or spoofed reply)
+    OriginMismatch,
+    /// Server clock not synchronised (leap indicator 3)
+    Unsynchronized,
+    /// Server sent no transmit timestamp
+    ZeroTransmitTime,
+    /// Server answered with a Kiss-o'-Death packet (stratum 0)
+    KissOfDeath(KissCode),
+    /// No majority of servers agree on the time (falsetickers)
+    NoMajority,
+}
+
+/// Kiss-o'-Death code sent by an NTP server (RFC 5905 §7.4)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+pub enum KissCode {
+    /// Rate exceeded: poll this server less often
+    Rate,
+    /// Access denied: stop querying this server
+    Deny,
+    /// Access restricted: stop querying this server
+    Rstr,
+    /// Any other (informational) four-character code
+    Other([u8; 4]),
 }
 
 // Automatic conversion from component errors to NetworkError
@@ -107,6 +204,10 @@ impl core::fmt::Display for NetworkError {
         match self {
             Self::DnsError => write!(f, "DNS resolution failed"),
             Self::SocketError => write!(f, "Socket error"),
+            Self::ConnectionRefused => write!(f, "Connection refused"),
+            Self::ConnectionReset => write!(f, "Connection reset"),
+            Self::NoRoute => write!(f, "No route to host"),
+            Self::Eof => write!(f, "Connection closed by peer"),
             Self::Timeout => write!(f, "Request timeout"),
             Self::InvalidResponse => write!(f, "Invalid response"),
             Self::ServerError => write!(f, "Server error"),
@@ -125,12 +226,46 @@ impl core::fmt::Display for TlsError {
         match self {
             Self::HandshakeFailed => write!(f, "handshake failed"),
             Self::CertificateError => write!(f, "certificate error"),
-            Self::AlertReceived => write!(f, "alert received"),
+            Self::PinMismatch => write!(f, "certificate pin mismatch"),
+            Self::BuffersTooSmall => write!(f, "TLS buffers too small"),
+            Self::FragmentLengthRefused => write!(f, "max fragment length refused"),
+            Self::ClientIdentityError => write!(f, "client identity error"),
+            Self::AlertReceived(alert) => write!(f, "alert received: {}", alert),
+            Self::DecodeError => write!(f, "decode error"),
+            Self::UnsupportedCipherSuite => write!(f, "unsupported cipher suite"),
             Self::ConnectionClosed => write!(f, "connection closed"),
+            Self::BuffersInUse => write!(f, "TLS buffers in use"),
         }
     }
 }
 
+impl core::fmt::Display for TlsAlert {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let name = match self {
+            Self::HandshakeFailure => "handshake_failure",
+            Self::BadCertificate => "bad_certificate",
+            Self::UnsupportedCertificate => "unsupported_certificate",
+            Self::CertificateRevoked => "certificate_revoked",
+            Self::CertificateExpired => "certificate_expired",
+            Self::CertificateUnknown => "certificate_unknown",
+            Self::UnknownCa => "unknown_ca",
+            Self::AccessDenied => "access_denied",
+            Self::DecodeError => "decode_error",
+            Self::DecryptError => "decrypt_error",
+            Self::ProtocolVersion => "protocol_version",
+            Self::InsufficientSecurity => "insufficient_security",
+            Self::IllegalParameter => "illegal_parameter",
+            Self::UnrecognizedName => "unrecognized_name",
+            Self::UnknownPskIdentity => "unknown_psk_identity",
+            Self::CertificateRequired => "certificate_required",
+            Self::NoApplicationProtocol => "no_application_protocol",
+            Self::InternalError => "internal_error",
+            Self::Other => "other",
+        };
+        f.write_str(name)
+    }
+}
+
 impl core::fmt::Display for MqttError {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
@@ -147,6 +282,24 @@ impl core::fmt::Display for SntpError {
         match self {
             Self::InvalidStratum => write!(f, "invalid stratum"),
             Self::ParseError => write!(f, "parse error"),
+            Self::InvalidMode => write!(f, "invalid mode"),
+            Self::UnsupportedVersion => write!(f, "unsupported version"),
+            Self::OriginMismatch => write!(f, "origin timestamp mismatch"),
+            Self::Unsynchronized => write!(f, "server unsynchronized"),
+            Self::ZeroTransmitTime => write!(f, "zero transmit timestamp"),
+            Self::KissOfDeath(code) => write!(f, "kiss-o'-death {}", code),
+            Self::NoMajority => write!(f, "no majority of servers agree"),
+        }
+    }
+}
+
+impl core::fmt::Display for KissCode {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Rate => f.write_str("RATE"),
+            Self::Deny => f.write_str("DENY"),
+            Self::Rstr => f.write_str("RSTR"),
+            Self::Other(code) => f.write_str(core::str::from_utf8(code).unwrap_or("????")),
         }
     }
 }
@@ -159,13 +312,67 @@ impl core::error::Error for SntpError {}
 
 impl embedded_io_async::Error for NetworkError {
     fn kind(&self) -> embedded_io_async::ErrorKind {
+        use embedded_io_async::ErrorKind;
+
         match self {
-            Self::SocketError | Self::Tls(TlsError::ConnectionClosed) => {
-                embedded_io_async::ErrorKind::BrokenPipe
-            }
-            Self::Timeout => embedded_io_async::ErrorKind::TimedOut,
-            Self::InvalidResponse => embedded_io_async::ErrorKind::InvalidData,
-            _ => embedded_io_async::ErrorKind::Other,
+            Self::SocketError | Self::Tls(TlsError::ConnectionClosed) => ErrorKind::BrokenPipe,
+            Self::ConnectionRefused => ErrorKind::ConnectionRefused,
+            Self::ConnectionReset => ErrorKind::ConnectionReset,
+            Self::NoRoute => ErrorKind::AddrNotAvailable,
+            Self::Eof | Self::Tls(TlsError::AlertReceived(_)) => ErrorKind::ConnectionAborted,
+            Self::Timeout => ErrorKind::TimedOut,
+            Self::InvalidResponse | Self::Tls(TlsError::DecodeError) => ErrorKind::InvalidData,
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
+/// Recover the transport error behind an `ErrorKind`
+///
+/// embedded-tls keeps only the kind of a socket error, so this inverts
+/// `kind()` for the errors `AsyncTcpSocket` produces.
+impl From<embedded_io_async::ErrorKind> for NetworkError {
+    fn from(kind: embedded_io_async::ErrorKind) -> Self {
+        use embedded_io_async::ErrorKind;
+
+        match kind {
+            ErrorKind::TimedOut => Self::Timeout,
+            ErrorKind::ConnectionRefused => Self::ConnectionRefused,
+            ErrorKind::ConnectionReset => Self::ConnectionReset,
+            ErrorKind::AddrNotAvailable => Self::NoRoute,
+            ErrorKind::ConnectionAborted => Self::Eof,
+            _ => Self::SocketError,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_io_async::Error;
+
+    #[test]
+    fn test_socket_errors_survive_error_kind() {
+        for e in [
+            NetworkError::Timeout,
+            NetworkError::ConnectionRefused,
+            NetworkError::ConnectionReset,
+            NetworkError::NoRoute,
+            NetworkError::Eof,
+        ] {
+            let recovered = NetworkError::from(e.kind());
+            assert_eq!(
+                core::mem::discriminant(&recovered),
+                core::mem::discriminant(&e)
+            );
+        }
+    }
+
+    #[test]
+    fn test_unknown_kind_is_socket_error() {
+        assert!(matches!(
+            NetworkError::from(embedded_io_async::ErrorKind::Other),
+            NetworkError::SocketError
+        ));
+    }
+}
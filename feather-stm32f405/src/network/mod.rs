@@ -2,13 +2,20 @@
 //! Network module with trait-based client architecture
 //!
 //! This module provides a modular network stack with:
+//! - **`backoff`**: Jittered exponential back-off for retry loops
 //! - **`client`**: `NetworkClient` trait for protocol implementations
 //! - **`config`**: Configuration structs with `Default` implementations
+//! - **`connector`**: `SecureConnector` (DNS → TCP → TLS 1.3) shared by all TLS clients
+//! - **`dns`**: Host name resolution returning every A/AAAA answer
 //! - **`error`**: Simple error enum for network operations
 //! - **`manager`**: W5500/embassy-net stack initialization
+//! - **`mqtt`**: MQTT v5.0 client, session and reconnect supervisor
+//! - **`ntp`**: NTPv4 packet format and offset/delay math (host-testable)
 //! - **`sntp`**: SNTP client implementing `NetworkClient`
 //! - **`socket`**: Async TCP socket wrapper for embedded-io-async
 //! - **`tls`**: TLS 1.3 client for secure communications
+//! - **`verifier`**: Server certificate verification against a CA bundle
+//! - **`x509`**: Minimal DER X.509 parsing and chain validation
 //!
 //! ## Architecture
 //!
@@ -32,26 +39,45 @@
 //! the W5500 device and runner. The `embassy-net` stack handles all TCP/IP
 //! protocol processing, and applications use `embassy-net`'s socket APIs directly.
 
+pub mod backoff;
 pub mod client;
 pub mod config;
+pub mod connector;
+pub mod dns;
 pub mod error;
 pub mod manager;
 pub mod mqtt;
+pub mod ntp;
 pub mod sntp;
 pub mod socket;
 pub mod tls;
+pub mod verifier;
+pub mod x509;
 
 // Re-export commonly used types
 pub use client::NetworkClient;
 #[allow(unused_imports)]
+pub use config::BackoffConfig;
+#[allow(unused_imports)]
 pub use config::NetworkConfig;
 #[allow(unused_imports)]
 pub use config::SntpConfig;
 #[allow(unused_imports)]
-pub use error::{MqttError, NetworkError, SntpError, TlsError};
+pub use config::TcpConfig;
+#[allow(unused_imports)]
+pub use connector::{SecureConnector, TlsStream};
+#[allow(unused_imports)]
+pub use error::{KissCode, MqttError, NetworkError, SntpError, TlsAlert, TlsError};
+#[allow(unused_imports)]
+pub use mqtt::{
+    DeliverySignal, InboundMessage, MqttBuffers, MqttClient, MqttConfig, MqttSession,
+    MqttSupervisor, OutboundMessage, Route, Subscriptions,
+};
+pub use sntp::{SntpClient, SyncResult};
+#[allow(unused_imports)]
+pub use tls::{CipherSuite, ClientIdentity, FragmentLength, PskCredentials};
 #[allow(unused_imports)]
-pub use mqtt::{MqttClient, MqttConfig};
-pub use sntp::SntpClient;
+pub use verifier::Pin;
 // TLS types are available but not re-exported yet (Phase 1)
 // Will be added when integrated into main.rs
 // pub use socket::AsyncTcpSocket;
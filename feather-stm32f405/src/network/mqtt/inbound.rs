@@ -0,0 +1,274 @@
+#![deny(unsafe_code)]
+#![deny(warnings)]
+//! Inbound MQTT messages: subscriptions, topic filters and dispatch (SR-NET-013)
+//!
+//! Subscriptions are registered before the supervisor starts and are (re)sent
+//! after every CONNACK. Each inbound PUBLISH is copied out of the session's bump
+//! buffer into an `InboundMessage` and routed to every subscription whose
+//! filter matches, either to a plain function handler or to an `rtic-sync`
+//! channel drained by another task. Dispatch never blocks the network loop: a
+//! full channel drops the message with a warning.
+//!
+//! # Example
+//!
+//! ```no_run
+//! let (cmd_tx, cmd_rx) = make_channel!(InboundMessage, INBOUND_QUEUE_DEPTH);
+//! let mut subscriptions = Subscriptions::new();
+//! let filter = mqtt::device_topic_filter("cmd/#")?;
+//! subscriptions.add(filter.as_str(), QoS::AtLeastOnce, Route::Channel(cmd_tx))?;
+//! ```
+
+use defmt::{debug, warn};
+use heapless::{String, Vec};
+use rust_mqtt::types::QoS;
+
+use super::supervisor::MAX_PAYLOAD_LEN;
+use super::MAX_TOPIC_LEN;
+use crate::network::error::MqttError;
+
+/// Maximum number of topic filters the device subscribes to
+pub const MAX_SUBSCRIPTIONS: usize = 4;
+
+/// Depth of each inbound message channel
+pub const INBOUND_QUEUE_DEPTH: usize = 4;
+
+/// Sending half of an inbound message channel (held by the supervisor)
+pub type InboundSender = rtic_sync::channel::Sender<'static, InboundMessage, INBOUND_QUEUE_DEPTH>;
+
+/// Receiving half of an inbound message channel (held by a consumer task)
+pub type InboundReceiver =
+    rtic_sync::channel::Receiver<'static, InboundMessage, INBOUND_QUEUE_DEPTH>;
+
+/// Synchronous message handler, called from the network task
+///
+/// Handlers must be short and non-blocking (SR-PERF-006: 50 ms budget).
+pub type MessageHandler = fn(&InboundMessage);
+
+/// Message received from the broker
+#[derive(Debug, Clone)]
+pub struct InboundMessage {
+    /// Topic the message was published to
+    pub topic: String<MAX_TOPIC_LEN>,
+    /// Message payload
+    pub payload: Vec<u8, MAX_PAYLOAD_LEN>,
+}
+
+impl InboundMessage {
+    /// Copy topic and payload out of the session buffers
+    ///
+    /// # Errors
+    ///
+    /// Returns `MqttError::BufferError` if the topic or payload is too long.
+    pub fn new(topic: &str, payload: &[u8]) -> Result<Self, MqttError> {
+        let mut topic_buf = String::new();
+        topic_buf
+            .push_str(topic)
+            .map_err(|_| MqttError::BufferError)?;
+        let payload = Vec::from_slice(payload).map_err(|_| MqttError::BufferError)?;
+
+        Ok(Self {
+            topic: topic_buf,
+            payload,
+        })
+    }
+}
+
+/// Destination for messages matching a subscription
+pub enum Route {
+    /// Call a function directly from the network task
+    Handler(MessageHandler),
+    /// Queue the message for another task
+    Channel(InboundSender),
+}
+
+/// A single topic filter subscription
+pub struct Subscription {
+    /// Topic filter (may contain `+` and `#` wildcards)
+    pub filter: String<MAX_TOPIC_LEN>,
+    /// Maximum QoS requested from the broker
+    pub qos: QoS,
+    /// Where matching messages are delivered
+    pub route: Route,
+}
+
+/// Set of subscriptions maintained across reconnects
+pub struct Subscriptions {
+    entries: Vec<Subscription, MAX_SUBSCRIPTIONS>,
+}
+
+impl Subscriptions {
+    /// Create an empty subscription set
+    pub const fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    /// Register a topic filter
+    ///
+    /// # Errors
+    ///
+    /// Returns `MqttError::ProtocolError` for an invalid filter and
+    /// `MqttError::BufferError` if the filter is too long or the table is full.
+    pub fn add(&mut self, filter: &str, qos: QoS, route: Route) -> Result<(), MqttError> {
+        if !is_valid_topic_filter(filter) {
+            return Err(MqttError::ProtocolError);
+        }
+
+        let mut filter_buf = String::new();
+        filter_buf
+            .push_str(filter)
+            .map_err(|_| MqttError::BufferError)?;
+
+        self.entries
+            .push(Subscription {
+                filter: filter_buf,
+                qos,
+                route,
+            })
+            .map_err(|_| MqttError::BufferError)
+    }
+
+    /// Iterate over registered subscriptions
+    pub fn iter(&self) -> impl Iterator<Item = &Subscription> {
+        self.entries.iter()
+    }
+
+    /// Deliver a message to every matching subscription
+    ///
+    /// Returns the number of routes the message was delivered to.
+    pub fn dispatch(&mut self, message: &InboundMessage) -> usize {
+        let mut delivered = 0;
+
+        for subscription in self.entries.iter_mut() {
+            if !topic_matches(&subscription.filter, &message.topic) {
+                continue;
+            }
+
+            match &mut subscription.route {
+                Route::Handler(handler) => {
+                    handler(message);
+                    delivered += 1;
+                }
+                Route::Channel(sender) => match sender.try_send(message.clone()) {
+                    Ok(()) => delivered += 1,
+                    Err(_) => warn!(
+                        "Inbound queue for '{}' full, dropping message on '{}'",
+                        subscription.filter.as_str(),
+                        message.topic.as_str()
+                    ),
+                },
+            }
+        }
+
+        if delivered == 0 {
+            debug!(
+                "No route for inbound message on '{}'",
+                message.topic.as_str()
+            );
+        }
+        delivered
+    }
+}
+
+impl Default for Subscriptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Check whether a string is a valid MQTT topic filter
+///
+/// Filters must be non-empty and free of null characters; `#` may only
+/// appear as the whole last level and `+` only as a whole level
+/// (MQTT v5.0 §4.7.1).
+pub fn is_valid_topic_filter(filter: &str) -> bool {
+    if filter.is_empty() || filter.contains('\0') {
+        return false;
+    }
+
+    let mut levels = filter.split('/').peekable();
+    while let Some(level) = levels.next() {
+        let is_last = levels.peek().is_none();
+        if level.contains('#') && (level != "#" || !is_last) {
+            return false;
+        }
+        if level.contains('+') && level != "+" {
+            return false;
+        }
+    }
+    true
+}
+
+/// Check whether a topic name matches a topic filter
+///
+/// Implements MQTT v5.0 §4.7 matching: `+` matches exactly one level, `#`
+/// matches the parent level and any number of child levels, and wildcards
+/// at the first level never match topics starting with `$`.
+pub fn topic_matches(filter: &str, topic: &str) -> bool {
+    if topic.starts_with('$') && (filter.starts_with('+') || filter.starts_with('#')) {
+        return false;
+    }
+
+    let mut filter_levels = filter.split('/');
+    let mut topic_levels = topic.split('/');
+
+    loop {
+        match (filter_levels.next(), topic_levels.next()) {
+            (Some("#"), _) => return true,
+            (Some("+"), Some(_)) => {}
+            (Some(f), Some(t)) if f == t => {}
+            (None, None) => return true,
+            _ => return false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_topic_filter_validation() {
+        assert!(is_valid_topic_filter("device/abc/cmd/#"));
+        assert!(is_valid_topic_filter("device/+/cmd"));
+        assert!(is_valid_topic_filter("#"));
+        assert!(is_valid_topic_filter("+"));
+
+        assert!(!is_valid_topic_filter(""));
+        assert!(!is_valid_topic_filter("device/#/cmd"));
+        assert!(!is_valid_topic_filter("device/cmd#"));
+        assert!(!is_valid_topic_filter("device/a+/cmd"));
+        assert!(!is_valid_topic_filter("device/\0"));
+    }
+
+    #[test]
+    fn test_exact_match() {
+        assert!(topic_matches("device/abc/cmd", "device/abc/cmd"));
+        assert!(!topic_matches("device/abc/cmd", "device/abc/cmd/reboot"));
+        assert!(!topic_matches("device/abc/cmd/reboot", "device/abc/cmd"));
+    }
+
+    #[test]
+    fn test_multi_level_wildcard() {
+        assert!(topic_matches("device/abc/cmd/#", "device/abc/cmd/reboot"));
+        assert!(topic_matches("device/abc/cmd/#", "device/abc/cmd/led/on"));
+        assert!(topic_matches("device/abc/cmd/#", "device/abc/cmd"));
+        assert!(!topic_matches("device/abc/cmd/#", "device/xyz/cmd/reboot"));
+        assert!(topic_matches("#", "device/abc"));
+    }
+
+    #[test]
+    fn test_single_level_wildcard() {
+        assert!(topic_matches("device/+/cmd", "device/abc/cmd"));
+        assert!(!topic_matches("device/+/cmd", "device/abc/def/cmd"));
+        assert!(topic_matches("device/+", "device/"));
+    }
+
+    #[test]
+    fn test_dollar_topics_not_matched_by_leading_wildcard() {
+        assert!(!topic_matches("#", "$SYS/broker/uptime"));
+        assert!(!topic_matches("+/broker/uptime", "$SYS/broker/uptime"));
+        assert!(topic_matches("$SYS/#", "$SYS/broker/uptime"));
+    }
+}
@@ -0,0 +1,477 @@
+#![deny(unsafe_code)]
+#![deny(warnings)]
+//! QoS 1 and QoS 2 in-flight tracking (SR-SENS-004)
+//!
+//! Every QoS 1/2 PUBLISH is kept in a bounded table until the broker has sent
+//! the acknowledgement that completes its flow:
+//!
+//! ```text
+//! QoS 1:  PUBLISH ──► PUBACK                        (Qos1Sent → done)
+//! QoS 2:  PUBLISH ──► PUBREC ──► PUBREL ──► PUBCOMP (Qos2Sent → Released → done)
+//! ```
+//!
+//! Packet identifiers are allocated by `rust-mqtt` when the PUBLISH is sent;
+//! the table refuses a second entry with the same identifier. The table lives
+//! in the supervisor, outside any single session, so unacknowledged messages
+//! survive a reconnect (MQTT v5.0 §4.4). When the broker kept the session,
+//! PUBLISHes without PUBREC are retransmitted with the DUP flag and PUBRELs
+//! are resent for messages awaiting PUBCOMP, all with their original packet
+//! identifiers. When it did not, every entry still waiting for PUBREC or
+//! PUBACK is marked stale and starts over as a new PUBLISH; QoS 2 messages
+//! past PUBREC are complete, since the broker already owns them and sending
+//! them again could deliver them twice. Stale entries keep their place in
+//! the table until republished, so a failure part-way through is retried on
+//! the next reconnect.
+//! A full table applies back-pressure: the supervisor stops draining the
+//! outbound queue until an entry completes.
+//!
+//! The table itself is transport-agnostic and host-testable; the supervisor
+//! feeds it acknowledgements from `MqttSession::read_event`.
+
+use defmt::{debug, warn, Format};
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::signal::Signal;
+use heapless::Vec;
+use rust_mqtt::types::QoS;
+
+use super::supervisor::OutboundMessage;
+use crate::network::error::MqttError;
+
+/// Maximum number of unacknowledged QoS 1/2 messages (send maximum)
+pub const MAX_INFLIGHT: usize = 4;
+
+/// Outcome of a confirmed publish
+pub type DeliveryResult = Result<(), MqttError>;
+
+/// Outcome of the confirmed publish identified by `tag`
+#[derive(Debug, Clone, Copy)]
+pub struct DeliveryReport {
+    /// Tag assigned by `publish_confirmed` to the message
+    pub tag: u32,
+    /// How the message's QoS flow ended
+    pub result: DeliveryResult,
+}
+
+/// Completion signal for a confirmed publish
+///
+/// Owned by the producing task (usually a `static`) and signalled by the
+/// supervisor once the message's QoS flow completes. Reports carry the
+/// message tag, so a late report for an abandoned message can be told apart
+/// from the one being waited for.
+pub type DeliverySignal = Signal<CriticalSectionRawMutex, DeliveryReport>;
+
+/// Acknowledgement packet received from the broker
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+pub enum AckKind {
+    /// PUBACK (QoS 1 complete)
+    Puback,
+    /// PUBREC (QoS 2, first half)
+    Pubrec,
+    /// PUBCOMP (QoS 2 complete)
+    Pubcomp,
+}
+
+/// Position of an in-flight message in its QoS flow
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+pub enum InflightState {
+    /// QoS 1 PUBLISH sent, waiting for PUBACK
+    Qos1Sent,
+    /// QoS 2 PUBLISH sent, waiting for PUBREC
+    Qos2Sent,
+    /// PUBREL sent, waiting for PUBCOMP
+    Released,
+}
+
+impl InflightState {
+    /// Initial state for a freshly sent PUBLISH, `None` for QoS 0
+    pub fn for_qos(qos: QoS) -> Option<Self> {
+        match qos {
+            QoS::AtMostOnce => None,
+            QoS::AtLeastOnce => Some(Self::Qos1Sent),
+            QoS::ExactlyOnce => Some(Self::Qos2Sent),
+        }
+    }
+
+    /// Whether the PUBLISH itself must be resent after a reconnect
+    ///
+    /// Once PUBREC has arrived the broker owns the message, so only the
+    /// PUBREL is resent and the payload must not be.
+    pub fn needs_republish(self) -> bool {
+        matches!(self, Self::Qos1Sent | Self::Qos2Sent)
+    }
+}
+
+/// Unacknowledged message held for retransmission
+pub struct InflightEntry {
+    /// Packet identifier assigned when the PUBLISH was first sent
+    pub packet_id: u16,
+    /// Current position in the QoS flow
+    pub state: InflightState,
+    /// Message to retransmit with DUP after a reconnect
+    pub message: OutboundMessage,
+    /// The broker's current session does not know `packet_id`, so the
+    /// message must be published again as new
+    pub stale: bool,
+}
+
+/// Result of feeding an acknowledgement into the table
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+pub enum AckOutcome {
+    /// The flow advanced but is not finished yet (PUBREC)
+    Advanced,
+    /// The flow finished and the entry was removed
+    Completed,
+    /// No entry is waiting for this acknowledgement
+    Unexpected,
+}
+
+/// Bounded table of QoS 1/2 messages awaiting acknowledgement
+pub struct InflightTable {
+    entries: Vec<InflightEntry, MAX_INFLIGHT>,
+}
+
+impl InflightTable {
+    /// Create an empty table
+    pub const fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    /// Number of messages awaiting acknowledgement
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether no messages are awaiting acknowledgement
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Whether another QoS 1/2 message can be accepted
+    pub fn is_full(&self) -> bool {
+        self.entries.is_full()
+    }
+
+    /// Track a PUBLISH that has just been written to the transport
+    ///
+    /// QoS 0 messages are not tracked; their delivery signal (if any) is
+    /// completed immediately. A message that cannot be tracked has its
+    /// delivery signal failed with the returned error.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MqttError::ProtocolError` if `packet_id` is already in flight
+    /// and `MqttError::BufferError` if the table is full.
+    pub fn insert(&mut self, packet_id: u16, message: OutboundMessage) -> Result<(), MqttError> {
+        let Some(state) = InflightState::for_qos(message.qos) else {
+            complete(&message, Ok(()));
+            return Ok(());
+        };
+
+        let result = if self.position(packet_id).is_some() {
+            warn!("Packet ID {} is already in flight", packet_id);
+            Err(MqttError::ProtocolError)
+        } else if self.is_full() {
+            Err(MqttError::BufferError)
+        } else {
+            Ok(())
+        };
+
+        match result {
+            Ok(()) => {
+                // Cannot fail: capacity was checked above
+                let _ = self.entries.push(InflightEntry {
+                    packet_id,
+                    state,
+                    message,
+                    stale: false,
+                });
+            }
+            Err(e) => complete(&message, Err(e)),
+        }
+        result
+    }
+
+    /// Advance the flow of `packet_id` with an acknowledgement
+    ///
+    /// Completed entries are removed and their delivery signal is set.
+    pub fn acknowledge(&mut self, kind: AckKind, packet_id: u16) -> AckOutcome {
+        let Some(index) = self.position(packet_id) else {
+            return AckOutcome::Unexpected;
+        };
+
+        let entry = &mut self.entries[index];
+        match (entry.state, kind) {
+            (InflightState::Qos1Sent, AckKind::Puback)
+            | (InflightState::Released, AckKind::Pubcomp) => {
+                let entry = self.entries.swap_remove(index);
+                debug!("Packet {} acknowledged ({:?})", packet_id, kind);
+                complete(&entry.message, Ok(()));
+                AckOutcome::Completed
+            }
+            (InflightState::Qos2Sent, AckKind::Pubrec) => {
+                entry.state = InflightState::Released;
+                AckOutcome::Advanced
+            }
+            _ => AckOutcome::Unexpected,
+        }
+    }
+
+    /// Drop `packet_id` after the broker refused it (reason code >= 0x80)
+    ///
+    /// Returns `true` if an entry was removed.
+    pub fn reject(&mut self, packet_id: u16) -> bool {
+        match self.position(packet_id) {
+            Some(index) => {
+                let entry = self.entries.swap_remove(index);
+                complete(&entry.message, Err(MqttError::PublishFailed));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Forget every packet identifier after the broker lost our session
+    ///
+    /// QoS 2 messages that already got their PUBREC are completed: the
+    /// broker took ownership of them and may have forwarded them, so
+    /// publishing them again would break exactly-once delivery. Every other
+    /// entry is marked stale, to be published again as a new message.
+    pub fn forget_session(&mut self) {
+        let mut index = 0;
+        while index < self.entries.len() {
+            let entry = &mut self.entries[index];
+            if entry.state.needs_republish() {
+                entry.stale = true;
+                index += 1;
+            } else {
+                let entry = self.entries.remove(index);
+                debug!(
+                    "Packet {} was already received by the broker",
+                    entry.packet_id
+                );
+                complete(&entry.message, Ok(()));
+            }
+        }
+    }
+
+    /// Track the stale entry at `index` under the identifier it was
+    /// published again with
+    ///
+    /// The QoS flow starts over and the entry is no longer stale.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MqttError::ProtocolError` if `packet_id` is already in
+    /// flight; the entry then stays stale so a later reconnect retries it.
+    pub fn renumber(&mut self, index: usize, packet_id: u16) -> Result<(), MqttError> {
+        if self.position(packet_id).is_some() {
+            warn!("Packet ID {} is already in flight", packet_id);
+            return Err(MqttError::ProtocolError);
+        }
+
+        let entry = &mut self.entries[index];
+        if let Some(state) = InflightState::for_qos(entry.message.qos) {
+            entry.state = state;
+        }
+        entry.packet_id = packet_id;
+        entry.stale = false;
+        Ok(())
+    }
+
+    /// Entries to retransmit after a reconnect
+    pub fn entries(&self) -> &[InflightEntry] {
+        &self.entries
+    }
+
+    /// Index of the entry the broker knows as `packet_id`
+    ///
+    /// Stale identifiers are ignored: the broker has never seen them in this
+    /// session, and the new allocator may hand them out again.
+    fn position(&self, packet_id: u16) -> Option<usize> {
+        self.entries
+            .iter()
+            .position(|e| !e.stale && e.packet_id == packet_id)
+    }
+}
+
+impl Default for InflightTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Signal the producer of `message`, if it asked to be told
+pub fn complete(message: &OutboundMessage, result: DeliveryResult) {
+    if let Some(delivery) = message.delivery {
+        delivery.signal(DeliveryReport {
+            tag: message.delivery_tag,
+            result,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(qos: QoS) -> OutboundMessage {
+        OutboundMessage::new("device/test/telemetry", b"{}", qos, false).unwrap()
+    }
+
+    #[test]
+    fn test_qos0_is_not_tracked() {
+        let mut table = InflightTable::new();
+        table.insert(0, message(QoS::AtMostOnce)).unwrap();
+        assert!(table.is_empty());
+    }
+
+    #[test]
+    fn test_qos1_completes_on_puback() {
+        let mut table = InflightTable::new();
+        table.insert(1, message(QoS::AtLeastOnce)).unwrap();
+
+        assert_eq!(
+            table.acknowledge(AckKind::Pubrec, 1),
+            AckOutcome::Unexpected
+        );
+        assert_eq!(table.acknowledge(AckKind::Puback, 1), AckOutcome::Completed);
+        assert!(table.is_empty());
+    }
+
+    #[test]
+    fn test_qos2_full_flow() {
+        let mut table = InflightTable::new();
+        table.insert(7, message(QoS::ExactlyOnce)).unwrap();
+
+        assert_eq!(
+            table.acknowledge(AckKind::Pubcomp, 7),
+            AckOutcome::Unexpected
+        );
+        assert_eq!(table.acknowledge(AckKind::Pubrec, 7), AckOutcome::Advanced);
+        assert_eq!(table.len(), 1);
+        assert_eq!(
+            table.acknowledge(AckKind::Pubcomp, 7),
+            AckOutcome::Completed
+        );
+        assert!(table.is_empty());
+    }
+
+    #[test]
+    fn test_unknown_packet_id() {
+        let mut table = InflightTable::new();
+        assert_eq!(
+            table.acknowledge(AckKind::Puback, 42),
+            AckOutcome::Unexpected
+        );
+        assert!(!table.reject(42));
+    }
+
+    #[test]
+    fn test_duplicate_packet_id_rejected() {
+        let mut table = InflightTable::new();
+        table.insert(3, message(QoS::AtLeastOnce)).unwrap();
+        assert!(matches!(
+            table.insert(3, message(QoS::AtLeastOnce)),
+            Err(MqttError::ProtocolError)
+        ));
+    }
+
+    #[test]
+    fn test_table_is_bounded() {
+        let mut table = InflightTable::new();
+        for id in 1..=MAX_INFLIGHT as u16 {
+            table.insert(id, message(QoS::AtLeastOnce)).unwrap();
+        }
+        assert!(table.is_full());
+        assert!(matches!(
+            table.insert(100, message(QoS::AtLeastOnce)),
+            Err(MqttError::BufferError)
+        ));
+    }
+
+    #[test]
+    fn test_forget_session_completes_received_qos2() {
+        static DELIVERY: DeliverySignal = DeliverySignal::new();
+
+        let mut table = InflightTable::new();
+        table
+            .insert(1, message(QoS::ExactlyOnce).with_delivery(&DELIVERY, 5))
+            .unwrap();
+        table.insert(2, message(QoS::AtLeastOnce)).unwrap();
+        table.acknowledge(AckKind::Pubrec, 1);
+
+        table.forget_session();
+        assert_eq!(table.len(), 1);
+        assert_eq!(table.entries()[0].packet_id, 2);
+        assert!(table.entries()[0].stale);
+
+        let report = DELIVERY.try_take().unwrap();
+        assert_eq!(report.tag, 5);
+        assert!(report.result.is_ok());
+    }
+
+    #[test]
+    fn test_stale_ids_ignored_until_renumbered() {
+        let mut table = InflightTable::new();
+        table.insert(1, message(QoS::AtLeastOnce)).unwrap();
+        table.forget_session();
+
+        // The broker does not know packet 1 any more
+        assert_eq!(
+            table.acknowledge(AckKind::Puback, 1),
+            AckOutcome::Unexpected
+        );
+
+        // A fresh allocator may hand the same identifier out again
+        table.renumber(0, 1).unwrap();
+        assert_eq!(table.acknowledge(AckKind::Puback, 1), AckOutcome::Completed);
+    }
+
+    #[test]
+    fn test_failed_restart_keeps_remaining_entries() {
+        static DELIVERIES: [DeliverySignal; 3] = [
+            DeliverySignal::new(),
+            DeliverySignal::new(),
+            DeliverySignal::new(),
+        ];
+
+        let mut table = InflightTable::new();
+        for (id, delivery) in (1..).zip(DELIVERIES.iter()) {
+            table
+                .insert(id, message(QoS::ExactlyOnce).with_delivery(delivery, 0))
+                .unwrap();
+        }
+        table.forget_session();
+
+        // The 1st entry is published again as packet 2, then publishing the
+        // 2nd fails and the session ends
+        table.renumber(0, 2).unwrap();
+
+        assert_eq!(table.len(), 3);
+        assert!(DELIVERIES.iter().all(|d| !d.signaled()));
+        let stale: Vec<u16, MAX_INFLIGHT> = table
+            .entries()
+            .iter()
+            .filter(|e| e.stale)
+            .map(|e| e.packet_id)
+            .collect();
+        assert_eq!(&stale[..], &[2, 3]);
+
+        // The next reconnect publishes all three again
+        table.forget_session();
+        for (index, id) in (0..3).zip(1..) {
+            table.renumber(index, id).unwrap();
+        }
+        assert!(table.entries().iter().all(|e| !e.stale));
+        assert!(DELIVERIES.iter().all(|d| !d.signaled()));
+    }
+
+    #[test]
+    fn test_needs_republish() {
+        assert!(InflightState::Qos1Sent.needs_republish());
+        assert!(InflightState::Qos2Sent.needs_republish());
+        assert!(!InflightState::Released.needs_republish());
+    }
+}
@@ -0,0 +1,1050 @@
+#![deny(warnings)]
+#![allow(dead_code)] // Phase 2: Will be used when integrated into main.rs
+//! MQTT v5.0 client implementation for Phase 2 network stack
+//!
+//! This module provides MQTT v5.0 client functionality using the `rust-mqtt` crate
+//! with TLS 1.3 transport. It integrates with the existing TLS infrastructure.
+//!
+//! # Sessions
+//!
+//! `MqttClient::connect` returns an `MqttSession`, which owns the TLS transport
+//! and the `rust-mqtt` client for as long as the connection is up. Callers keep
+//! the session around and call `MqttSession::publish` as often as needed; DNS,
+//! TCP and the TLS handshake only happen once per session.
+//!
+//! `supervisor::MqttSupervisor` wraps this in a reconnect loop with jittered
+//! exponential back-off (SR-NET-003), publishes messages queued by other RTIC
+//! tasks and runs the event-driven receive loop (SR-NET-013) that dispatches
+//! inbound PUBLISH packets to the handlers registered in `inbound`. QoS 1/2
+//! messages are tracked in `inflight` until acknowledged and retransmitted with
+//! the DUP flag after a reconnect.
+//!
+//! # Memory Management
+//!
+//! Uses bump allocator pattern from `rust-mqtt` for no_std compatibility:
+//! - MQTT packet buffer: 2KB for packet assembly
+//! - TLS buffers: ~8.5KB total (managed by `SecureConnector`)
+//! - TCP buffers: 8KB total (provided through `MqttBuffers`)
+//!
+//! # Example
+//!
+//! ```no_run
+//! let config = MqttConfig {
+//!     broker_host: "192.168.1.1",
+//!     broker_port: 8883,
+//!     keep_alive_secs: 60,
+//!     clean_start: true,
+//!     ..Default::default()
+//! };
+//! let client = MqttClient::new(config);
+//! let mut buffers = MqttBuffers::new(&mut packet_buf, &mut rx_buf, &mut tx_buf);
+//! let mut session = client.connect(stack, &mut rng, &mut buffers).await?;
+//! session.publish("device/test", b"Hello!", QoS::AtMostOnce, false).await?;
+//! session.publish("device/test", b"Hello again!", QoS::AtMostOnce, false).await?;
+//! ```
+
+#![allow(unsafe_code)] // Required for unchecked topic name/filter construction
+
+use defmt::{debug, error, info, warn, Debug2Format};
+use embassy_net::Stack;
+use heapless::String;
+use rust_mqtt::{
+    buffer::BumpBuffer,
+    client::{
+        event::Event,
+        options::{
+            ConnectOptions, DisconnectOptions, PublicationOptions, SubscriptionOptions,
+            TopicReference, WillOptions,
+        },
+        Client,
+    },
+    config::{KeepAlive, SessionExpiryInterval},
+    header::FixedHeader,
+    types::{MqttBinary, MqttString, QoS, TopicFilter, TopicName},
+    Bytes,
+};
+
+use crate::device_id;
+use crate::tls_buffers;
+
+use super::config::{BackoffConfig, TcpConfig};
+use super::connector::{SecureConnector, TlsStream};
+use super::error::{MqttError, NetworkError};
+use super::tls::{CipherSuite, ClientIdentity, FragmentLength, PskCredentials, TlsClientConfig};
+use super::verifier::Pin;
+
+pub mod inbound;
+pub mod inflight;
+pub mod supervisor;
+
+pub use inbound::{InboundMessage, Route, Subscriptions};
+pub use inflight::{AckKind, DeliveryReport, DeliveryResult, DeliverySignal};
+pub use supervisor::{ConnectionState, MqttSupervisor, OutboundMessage};
+
+/// MQTT packet buffer size: 2KB for packet assembly
+pub const MQTT_BUFFER_SIZE: usize = 2048;
+
+/// TCP socket buffer size (each direction) for the MQTT transport
+pub const MQTT_TCP_BUFFER_SIZE: usize = 4096;
+
+/// Maximum MQTT topic length
+/// Format: "device/{client_id}/telemetry" where client_id is ~34 chars
+/// Total: 7 + 34 + 10 = 51 chars, use 64 for safety
+pub const MAX_TOPIC_LEN: usize = 64;
+
+/// TLS transport carried by an MQTT session
+type MqttTransport<'a> = TlsStream<'a>;
+
+/// rust-mqtt client bound to the TLS transport and bump buffer of one session
+///
+/// Const parameters: up to `MAX_SUBSCRIPTIONS` pending subscriptions,
+/// receive maximum 1, send maximum `MAX_INFLIGHT`, no subscription identifiers.
+type SessionClient<'a> = Client<
+    'a,
+    MqttTransport<'a>,
+    BumpBuffer<'a>,
+    { inbound::MAX_SUBSCRIPTIONS },
+    1,
+    { inflight::MAX_INFLIGHT },
+    0,
+>;
+
+/// Per-device status subtopic carrying birth and will messages
+pub const STATUS_SUBTOPIC: &str = "status";
+
+/// Retained birth payload published on `device/{id}/status` after CONNACK
+pub const ONLINE_PAYLOAD: &[u8] = b"online";
+
+/// Will payload the broker publishes on `device/{id}/status` for us
+pub const OFFLINE_PAYLOAD: &[u8] = b"offline";
+
+/// ALPN protocol AWS IoT Core requires for MQTT with X.509 auth on port 443
+pub const AWS_IOT_ALPN: &[u8] = b"x-amzn-mqtt-ca";
+
+/// `MqttConfig::alpn_protocols` value for AWS IoT Core
+pub const AWS_IOT_ALPN_PROTOCOLS: &[&[u8]] = &[AWS_IOT_ALPN];
+
+/// Last Will and Testament registered with the broker at CONNECT
+///
+/// The broker publishes the will if the session ends without a normal
+/// DISCONNECT (keep-alive expiry, TCP reset, power loss), after
+/// `delay_secs` have passed without the device reconnecting.
+#[derive(Clone, Copy)]
+pub struct WillConfig {
+    /// Topic suffix under `device/{id}/` (e.g. "status")
+    pub subtopic: &'static str,
+    /// Will payload
+    pub payload: &'static [u8],
+    /// QoS the broker uses to publish the will
+    pub qos: QoS,
+    /// Whether the broker retains the will
+    pub retain: bool,
+    /// Will delay interval in seconds (0 = publish immediately)
+    pub delay_secs: u32,
+}
+
+impl WillConfig {
+    /// Retained "offline" will on `device/{id}/status`, pairing with the
+    /// retained "online" birth message
+    pub const fn offline_status(delay_secs: u32) -> Self {
+        Self {
+            subtopic: STATUS_SUBTOPIC,
+            payload: OFFLINE_PAYLOAD,
+            qos: QoS::AtLeastOnce,
+            retain: true,
+            delay_secs,
+        }
+    }
+}
+
+/// MQTT client configuration
+#[derive(Clone, Copy)]
+pub struct MqttConfig {
+    /// Broker hostname (for DNS and SNI)
+    pub broker_host: &'static str,
+    /// Broker port (typically 8883 for MQTTS)
+    pub broker_port: u16,
+    /// Keep-alive interval in seconds
+    pub keep_alive_secs: u16,
+    /// Clean start flag (true = new session)
+    pub clean_start: bool,
+    /// Session expiry interval in seconds (0 = session ends on disconnect)
+    ///
+    /// Combine a non-zero value with `clean_start: false` so the broker keeps
+    /// QoS 1/2 state across reconnects.
+    pub session_expiry_secs: u32,
+    /// Last Will and Testament (`None` = no will)
+    pub will: Option<WillConfig>,
+    /// Publish a retained `ONLINE_PAYLOAD` on `device/{id}/status` after
+    /// every CONNACK (done by `MqttSupervisor`)
+    pub birth_message: bool,
+    /// User name sent in CONNECT (`None` = anonymous)
+    pub username: Option<&'static str>,
+    /// Password sent in CONNECT; only used together with `username`
+    ///
+    /// MQTT v5.0 enhanced authentication (AUTH packet exchange) is not
+    /// supported by `rust-mqtt`, so brokers must accept user name/password
+    /// (or client certificate) authentication.
+    pub password: Option<&'static [u8]>,
+    /// Trusted CA certificates (DER) for verifying the broker
+    ///
+    /// An empty slice disables verification, which is only acceptable for
+    /// local test brokers.
+    pub ca_certificates: &'static [&'static [u8]],
+    /// SHA-256 pins of the broker certificate or public key, checked in
+    /// addition to (or, without a CA bundle, instead of) chain validation
+    pub pins: &'static [Pin],
+    /// Device certificate and key for mutual TLS (required by AWS IoT Core)
+    pub client_identity: Option<ClientIdentity>,
+    /// TLS 1.3 pre-shared key instead of certificates (edge gateways)
+    pub psk: Option<PskCredentials>,
+    /// TLS cipher suite for the broker connection
+    pub cipher_suite: CipherSuite,
+    /// TLS Max Fragment Length; must fit `tls_buffers::FRAGMENT_LENGTH`
+    pub max_fragment_length: Option<FragmentLength>,
+    /// ALPN protocols to offer (`AWS_IOT_ALPN_PROTOCOLS` for AWS IoT on 443)
+    pub alpn_protocols: &'static [&'static [u8]],
+    /// Port to try when `broker_port` is unreachable (443 for AWS IoT)
+    pub fallback_port: Option<u16>,
+    /// TCP timeouts and keep-alive for the broker connection
+    ///
+    /// Keep `read_timeout_ms` unset: the supervisor waits for inbound packets
+    /// indefinitely and relies on MQTT keep-alive to detect a dead broker.
+    pub tcp: TcpConfig,
+    /// Deadline for the TLS handshake in milliseconds
+    pub handshake_timeout_ms: u64,
+    /// Reconnect back-off used by `MqttSupervisor`
+    pub reconnect: BackoffConfig,
+}
+
+impl Default for MqttConfig {
+    fn default() -> Self {
+        Self {
+            broker_host: "192.168.1.1",
+            broker_port: 8883,
+            keep_alive_secs: 60,
+            clean_start: true,
+            session_expiry_secs: 0,
+            will: None,
+            birth_message: false,
+            username: None,
+            password: None,
+            ca_certificates: &[],
+            pins: &[],
+            client_identity: None,
+            psk: None,
+            cipher_suite: CipherSuite::default(),
+            max_fragment_length: tls_buffers::FRAGMENT_LENGTH,
+            alpn_protocols: &[],
+            fallback_port: None,
+            tcp: TcpConfig::default(),
+            handshake_timeout_ms: 15_000,
+            reconnect: BackoffConfig::default(),
+        }
+    }
+}
+
+/// Buffers backing a single MQTT session
+///
+/// The session borrows these for its whole lifetime: the bump buffer is used
+/// by `rust-mqtt` for packet assembly and the TCP buffers back the socket
+/// underneath the TLS connection. Dropping the session releases the borrow so
+/// the same buffers can be reused for the next connection.
+pub struct MqttBuffers<'a> {
+    bump: BumpBuffer<'a>,
+    tcp_rx: &'a mut [u8],
+    tcp_tx: &'a mut [u8],
+}
+
+impl<'a> MqttBuffers<'a> {
+    /// Wrap caller-provided storage for an MQTT session
+    ///
+    /// # Arguments
+    ///
+    /// * `packet` - MQTT packet assembly buffer (typically `MQTT_BUFFER_SIZE`)
+    /// * `tcp_rx` - TCP receive buffer (typically `MQTT_TCP_BUFFER_SIZE`)
+    /// * `tcp_tx` - TCP transmit buffer (typically `MQTT_TCP_BUFFER_SIZE`)
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use static_cell::StaticCell;
+    /// static MQTT_BUF: StaticCell<[u8; 2048]> = StaticCell::new();
+    /// static RX_BUF: StaticCell<[u8; 4096]> = StaticCell::new();
+    /// static TX_BUF: StaticCell<[u8; 4096]> = StaticCell::new();
+    ///
+    /// let mut buffers = MqttBuffers::new(
+    ///     MQTT_BUF.init([0u8; 2048]),
+    ///     RX_BUF.init([0u8; 4096]),
+    ///     TX_BUF.init([0u8; 4096]),
+    /// );
+    /// ```
+    pub fn new(packet: &'a mut [u8], tcp_rx: &'a mut [u8], tcp_tx: &'a mut [u8]) -> Self {
+        Self {
+            bump: BumpBuffer::new(packet),
+            tcp_rx,
+            tcp_tx,
+        }
+    }
+}
+
+/// Statically allocated storage for MQTT sessions
+///
+/// Owns the raw packet and TCP buffers so that a reconnect loop can hand out
+/// a fresh `MqttBuffers` view for every connection attempt.
+pub struct MqttStorage {
+    packet: [u8; MQTT_BUFFER_SIZE],
+    tcp_rx: [u8; MQTT_TCP_BUFFER_SIZE],
+    tcp_tx: [u8; MQTT_TCP_BUFFER_SIZE],
+}
+
+impl MqttStorage {
+    /// Create zeroed storage (intended for a `StaticCell`)
+    pub const fn new() -> Self {
+        Self {
+            packet: [0; MQTT_BUFFER_SIZE],
+            tcp_rx: [0; MQTT_TCP_BUFFER_SIZE],
+            tcp_tx: [0; MQTT_TCP_BUFFER_SIZE],
+        }
+    }
+
+    /// Borrow the storage for one session
+    pub fn buffers(&mut self) -> MqttBuffers<'_> {
+        MqttBuffers::new(&mut self.packet, &mut self.tcp_rx, &mut self.tcp_tx)
+    }
+}
+
+impl Default for MqttStorage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// MQTT v5.0 client
+///
+/// Manages MQTT connections over TLS 1.3. The client handles:
+/// - Connection establishment with automatic TLS handshake
+/// - Keep-alive and clean session negotiation
+///
+/// Publishing happens on the `MqttSession` returned by `connect`.
+pub struct MqttClient {
+    config: MqttConfig,
+}
+
+impl MqttClient {
+    /// Create a new MQTT client with the given configuration
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// let config = MqttConfig {
+    ///     broker_host: "192.168.1.1",
+    ///     broker_port: 8883,
+    ///     keep_alive_secs: 60,
+    ///     clean_start: true,
+    ///     ..Default::default()
+    /// };
+    /// let client = MqttClient::new(config);
+    /// ```
+    pub fn new(config: MqttConfig) -> Self {
+        Self { config }
+    }
+
+    /// Configuration this client connects with
+    pub fn config(&self) -> &MqttConfig {
+        &self.config
+    }
+
+    /// TLS settings for the broker connection
+    pub fn tls_config(&self) -> TlsClientConfig {
+        TlsClientConfig {
+            server_name: self.config.broker_host,
+            server_port: self.config.broker_port,
+            verify_server: !self.config.ca_certificates.is_empty() || !self.config.pins.is_empty(),
+            ca_certificates: self.config.ca_certificates,
+            pins: self.config.pins,
+            client_identity: self.config.client_identity,
+            psk: self.config.psk,
+            cipher_suite: self.config.cipher_suite,
+            max_fragment_length: self.config.max_fragment_length,
+            alpn_protocols: self.config.alpn_protocols,
+            fallback_port: self.config.fallback_port,
+            tcp: self.config.tcp,
+            handshake_timeout_ms: self.config.handshake_timeout_ms,
+        }
+    }
+
+    /// Connect to the MQTT broker over TLS 1.3
+    ///
+    /// This function:
+    /// 1. Resolves the broker hostname, connects and performs the TLS 1.3
+    ///    handshake through `SecureConnector`
+    /// 2. Sends MQTT CONNECT packet
+    /// 3. Waits for CONNACK
+    ///
+    /// # Arguments
+    ///
+    /// * `stack` - Embassy network stack for DNS and TCP operations
+    /// * `rng` - Hardware random number generator (STM32F405 RNG peripheral)
+    /// * `buffers` - Packet and TCP buffers, borrowed for the session lifetime
+    ///
+    /// # Returns
+    ///
+    /// Returns a connected `MqttSession`, or a `NetworkError` if any step fails.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// let client = MqttClient::new(MqttConfig::default());
+    /// let mut session = client.connect(stack, &mut rng, &mut buffers).await?;
+    /// ```
+    pub async fn connect<'a, RNG>(
+        &self,
+        stack: &Stack<'static>,
+        rng: &mut RNG,
+        buffers: &'a mut MqttBuffers<'a>,
+    ) -> Result<MqttSession<'a>, NetworkError>
+    where
+        RNG: rand_core::RngCore + rand_core::CryptoRng,
+    {
+        info!(
+            "Connecting to MQTT broker at {}:{}",
+            self.config.broker_host, self.config.broker_port
+        );
+
+        let MqttBuffers {
+            bump,
+            tcp_rx,
+            tcp_tx,
+        } = buffers;
+
+        // Steps 1-3: DNS, TCP and TLS handshake on the session buffers
+        let transport = SecureConnector::new(self.tls_config())
+            .connect(stack, rng, &mut **tcp_rx, &mut **tcp_tx)
+            .await?;
+        if transport.remote_port() != self.config.broker_port {
+            info!("Connected on fallback port {}", transport.remote_port());
+        }
+        for protocol in transport.offered_alpn() {
+            info!("Offered ALPN protocol: {=[u8]:a}", protocol);
+        }
+
+        // Step 4: Establish MQTT connection
+        let client_id = device_id::mqtt_client_id();
+        info!("MQTT client ID: {}", client_id);
+
+        let mut mqtt_client = SessionClient::new(bump);
+
+        let (user_name, password) = credentials(&self.config)?;
+
+        // The will topic must outlive the CONNECT packet
+        let will_topic = match &self.config.will {
+            Some(will) => Some(format_mqtt_topic(client_id.as_str(), will.subtopic)?),
+            None => None,
+        };
+        let will = match (&self.config.will, &will_topic) {
+            (Some(will), Some(topic)) => {
+                info!(
+                    "Registering will on '{}' (delay {} s)",
+                    topic.as_str(),
+                    will.delay_secs
+                );
+                Some(will_options(will, topic.as_str())?)
+            }
+            _ => None,
+        };
+
+        let connect_opts = ConnectOptions {
+            session_expiry_interval: if self.config.session_expiry_secs == 0 {
+                SessionExpiryInterval::EndOnDisconnect
+            } else {
+                SessionExpiryInterval::Seconds(self.config.session_expiry_secs)
+            },
+            clean_start: self.config.clean_start,
+            keep_alive: if self.config.keep_alive_secs == 0 {
+                KeepAlive::Infinite
+            } else {
+                KeepAlive::Seconds(self.config.keep_alive_secs)
+            },
+            will,
+            user_name,
+            password,
+        };
+
+        // Convert client_id to MqttString
+        let mqtt_client_id = MqttString::new(client_id.as_str().into()).map_err(|e| {
+            error!(
+                "Failed to create MQTT client ID string: {:?}",
+                Debug2Format(&e)
+            );
+            MqttError::ProtocolError
+        })?;
+
+        let connack = mqtt_client
+            .connect(transport, &connect_opts, Some(mqtt_client_id))
+            .await
+            .map_err(|e| {
+                error!("MQTT connect failed: {:?}", Debug2Format(&e));
+                MqttError::ConnectionFailed
+            })?;
+
+        info!(
+            "MQTT connection established successfully! (session present: {})",
+            connack.session_present
+        );
+
+        Ok(MqttSession {
+            client: mqtt_client,
+            client_id,
+            has_will: self.config.will.is_some(),
+            session_present: connack.session_present,
+        })
+    }
+}
+
+/// Packet received on a session, translated from `rust-mqtt` events
+#[derive(Debug)]
+pub enum SessionEvent {
+    /// Application message published by the broker
+    Message(InboundMessage),
+    /// Broker answered a PINGREQ
+    Pingresp,
+    /// Broker acknowledged a SUBSCRIBE
+    Subscribed,
+    /// Broker acknowledged one step of an outgoing QoS 1/2 flow
+    Ack(AckKind, u16),
+    /// Broker refused an outgoing QoS 1/2 PUBLISH
+    Rejected(u16),
+    /// Any other packet (already handled by `rust-mqtt`), or an inbound
+    /// message dropped because it does not fit `InboundMessage`
+    Other,
+}
+
+/// Connected MQTT session
+///
+/// Owns the TLS transport and the `rust-mqtt` client (which in turn holds the
+/// session's bump buffer). The connection stays open until the session is
+/// dropped or `disconnect` is called, so `publish` can be called repeatedly
+/// without repeating DNS, TCP and TLS setup.
+pub struct MqttSession<'a> {
+    client: SessionClient<'a>,
+    client_id: String<{ device_id::CLIENT_ID_MAX_LEN }>,
+    has_will: bool,
+    session_present: bool,
+}
+
+impl<'a> MqttSession<'a> {
+    /// MQTT client ID this session was established with
+    pub fn client_id(&self) -> &str {
+        self.client_id.as_str()
+    }
+
+    /// Whether the broker resumed existing session state (CONNACK flag)
+    ///
+    /// When `false`, the broker knows nothing of earlier in-flight messages
+    /// and their QoS flows must start again from PUBLISH.
+    pub fn session_present(&self) -> bool {
+        self.session_present
+    }
+
+    /// Build a per-device topic for this session
+    ///
+    /// Returns `device/{client_id}/{subtopic}`.
+    pub fn device_topic(&self, subtopic: &str) -> Result<String<MAX_TOPIC_LEN>, MqttError> {
+        format_mqtt_topic(self.client_id.as_str(), subtopic)
+    }
+
+    /// Publish a message to an MQTT topic
+    ///
+    /// # Arguments
+    ///
+    /// * `topic` - Topic name (e.g., "device/status"); wildcards are rejected
+    /// * `payload` - Message payload bytes
+    /// * `qos` - Quality of Service level
+    /// * `retain` - Whether to retain the message on the broker
+    ///
+    /// # Returns
+    ///
+    /// Returns the packet identifier once the PUBLISH packet has been written
+    /// to the transport, or a `NetworkError` if it fails. For QoS 1/2 the
+    /// acknowledgement arrives later through `read_event` as
+    /// `SessionEvent::Ack`; the caller tracks it (see `inflight`).
+    pub async fn publish(
+        &mut self,
+        topic: &str,
+        payload: &[u8],
+        qos: QoS,
+        retain: bool,
+    ) -> Result<u16, NetworkError> {
+        let pub_options = publication_options(topic, qos, retain)?;
+
+        let packet_id = self
+            .client
+            .publish(&pub_options, Bytes::from(payload))
+            .await
+            .map_err(|e| {
+                error!("MQTT publish to '{}' failed: {:?}", topic, Debug2Format(&e));
+                MqttError::PublishFailed
+            })?;
+
+        debug!("Published to '{}' (packet_id: {})", topic, packet_id);
+        Ok(packet_id)
+    }
+
+    /// Retransmit an unacknowledged QoS 1/2 PUBLISH with the DUP flag set
+    ///
+    /// Reuses the packet identifier from the original transmission, as
+    /// required after a reconnect (MQTT v5.0 §4.4).
+    pub async fn republish(
+        &mut self,
+        packet_id: u16,
+        topic: &str,
+        payload: &[u8],
+        qos: QoS,
+        retain: bool,
+    ) -> Result<(), NetworkError> {
+        let pub_options = publication_options(topic, qos, retain)?;
+
+        self.client
+            .republish(packet_id, &pub_options, Bytes::from(payload))
+            .await
+            .map_err(|e| {
+                error!(
+                    "MQTT republish of packet {} failed: {:?}",
+                    packet_id,
+                    Debug2Format(&e)
+                );
+                MqttError::PublishFailed
+            })?;
+
+        info!("Retransmitted packet {} to '{}' (DUP)", packet_id, topic);
+        Ok(())
+    }
+
+    /// Resend the PUBREL of a QoS 2 flow interrupted after PUBREC
+    ///
+    /// Required after a reconnect with a resumed session (MQTT v5.0 §4.4);
+    /// the PUBCOMP arrives through `read_event`.
+    pub async fn release(&mut self, packet_id: u16) -> Result<(), NetworkError> {
+        self.client.rerelease(packet_id).await.map_err(|e| {
+            error!(
+                "MQTT PUBREL for packet {} failed: {:?}",
+                packet_id,
+                Debug2Format(&e)
+            );
+            MqttError::PublishFailed
+        })?;
+
+        info!("Resent PUBREL for packet {}", packet_id);
+        Ok(())
+    }
+
+    /// Subscribe to a topic filter
+    ///
+    /// Only sends the SUBSCRIBE packet; the SUBACK arrives later through
+    /// `read_event` like any other inbound packet.
+    pub async fn subscribe(&mut self, filter: &str, qos: QoS) -> Result<(), NetworkError> {
+        let topic_filter = topic_filter(filter)?;
+        let options = SubscriptionOptions {
+            qos,
+            ..Default::default()
+        };
+
+        let packet_id = self
+            .client
+            .subscribe(topic_filter, options)
+            .await
+            .map_err(|e| {
+                error!(
+                    "MQTT subscribe to '{}' failed: {:?}",
+                    filter,
+                    Debug2Format(&e)
+                );
+                MqttError::ProtocolError
+            })?;
+
+        info!("Subscribing to '{}' (packet_id: {})", filter, packet_id);
+        Ok(())
+    }
+
+    /// Send a PINGREQ
+    ///
+    /// The PINGRESP is delivered through `read_event` as
+    /// `SessionEvent::Pingresp`; the caller tracks the response deadline.
+    pub async fn send_ping(&mut self) -> Result<(), NetworkError> {
+        self.client.ping().await.map_err(|e| {
+            error!("MQTT PINGREQ failed: {:?}", Debug2Format(&e));
+            MqttError::ConnectionFailed
+        })?;
+        debug!("PINGREQ sent");
+        Ok(())
+    }
+
+    /// Wait for the fixed header of the next inbound packet
+    ///
+    /// `rust-mqtt` guarantees this step is cancel-safe, so it can be raced
+    /// against outbound work in a `select`. The body must then be read with
+    /// `read_event` without cancellation.
+    pub async fn poll_header(&mut self) -> Result<FixedHeader, NetworkError> {
+        self.client.poll_header().await.map_err(|e| {
+            error!("MQTT receive failed: {:?}", Debug2Format(&e));
+            NetworkError::from(MqttError::ConnectionFailed)
+        })
+    }
+
+    /// Read the body of a packet announced by `poll_header`
+    ///
+    /// Acknowledgements required by the protocol are sent by `rust-mqtt`.
+    pub async fn read_event(&mut self, header: FixedHeader) -> Result<SessionEvent, NetworkError> {
+        let event = self.client.poll_body(header).await.map_err(|e| {
+            error!("MQTT receive failed: {:?}", Debug2Format(&e));
+            MqttError::ConnectionFailed
+        })?;
+
+        let event = match event {
+            // rust-mqtt has already acknowledged the PUBLISH, so a message
+            // too large for the inbound queue is dropped rather than ending
+            // the session (the broker would redeliver it on every reconnect)
+            Event::Publish(publication) => {
+                match InboundMessage::new(publication.topic.as_str(), &publication.message) {
+                    Ok(message) => SessionEvent::Message(message),
+                    Err(e) => {
+                        warn!(
+                            "Dropping inbound message on '{}' ({} bytes): {:?}",
+                            publication.topic.as_str(),
+                            publication.message.len(),
+                            e
+                        );
+                        SessionEvent::Other
+                    }
+                }
+            }
+            Event::Pingresp => SessionEvent::Pingresp,
+            Event::PublishAcknowledged(ack) => {
+                SessionEvent::Ack(AckKind::Puback, ack.packet_identifier)
+            }
+            Event::PublishReceived(ack) => {
+                SessionEvent::Ack(AckKind::Pubrec, ack.packet_identifier)
+            }
+            Event::PublishComplete(ack) => {
+                SessionEvent::Ack(AckKind::Pubcomp, ack.packet_identifier)
+            }
+            Event::PublishRejected(rejection) => {
+                warn!(
+                    "Broker rejected packet {}: {:?}",
+                    rejection.packet_identifier,
+                    Debug2Format(&rejection.reason_code)
+                );
+                SessionEvent::Rejected(rejection.packet_identifier)
+            }
+            Event::Suback(suback) => {
+                info!("SUBACK received: {:?}", Debug2Format(&suback));
+                SessionEvent::Subscribed
+            }
+            other => {
+                debug!("MQTT event: {:?}", Debug2Format(&other));
+                SessionEvent::Other
+            }
+        };
+        Ok(event)
+    }
+
+    /// Gracefully close the session with an MQTT DISCONNECT
+    ///
+    /// If a will was registered, the DISCONNECT asks the broker to publish it
+    /// anyway (reason code 0x04), so a planned shutdown is reported as
+    /// "offline" just like a lost connection.
+    ///
+    /// Consumes the session; the buffers become available again afterwards.
+    pub async fn disconnect(mut self) -> Result<(), NetworkError> {
+        let options = DisconnectOptions {
+            publish_will: self.has_will,
+            session_expiry_interval: None,
+        };
+
+        self.client.disconnect(&options).await.map_err(|e| {
+            warn!("MQTT disconnect failed: {:?}", Debug2Format(&e));
+            MqttError::ConnectionFailed
+        })?;
+
+        info!("MQTT session closed");
+        Ok(())
+    }
+}
+
+/// Build the CONNECT user name and password from the configuration
+///
+/// A password without a user name is dropped with a warning: MQTT v5.0
+/// allows it, but brokers such as Mosquitto reject it.
+fn credentials(
+    config: &MqttConfig,
+) -> Result<(Option<MqttString<'static>>, Option<MqttBinary<'static>>), MqttError> {
+    let Some(username) = config.username else {
+        if config.password.is_some() {
+            warn!("MQTT password configured without user name; ignoring it");
+        }
+        return Ok((None, None));
+    };
+
+    let user_name = MqttString::new(username.into()).map_err(|e| {
+        error!("Invalid MQTT user name: {:?}", Debug2Format(&e));
+        MqttError::ProtocolError
+    })?;
+
+    // Never log the password itself
+    let password = match config.password {
+        Some(password) => Some(MqttBinary::try_from(Bytes::from(password)).map_err(|_| {
+            error!("MQTT password too long");
+            MqttError::ProtocolError
+        })?),
+        None => None,
+    };
+
+    info!("Authenticating as MQTT user '{}'", username);
+    Ok((Some(user_name), password))
+}
+
+/// Build `rust-mqtt` will options for a validated will topic
+fn will_options<'w>(will: &WillConfig, topic: &'w str) -> Result<WillOptions<'w>, MqttError> {
+    let payload = MqttBinary::try_from(Bytes::from(will.payload)).map_err(|e| {
+        error!("Will payload too long: {:?}", Debug2Format(&e));
+        MqttError::BufferError
+    })?;
+
+    Ok(WillOptions {
+        will_qos: will.qos,
+        will_retain: will.retain,
+        will_topic: topic_name(topic)?,
+        will_payload: payload,
+        will_delay_interval: will.delay_secs,
+        is_payload_utf8: false,
+        message_expiry_interval: None,
+        content_type: None,
+        response_topic: None,
+        correlation_data: None,
+    })
+}
+
+/// Build `rust-mqtt` publication options for a validated topic
+fn publication_options(
+    topic: &str,
+    qos: QoS,
+    retain: bool,
+) -> Result<PublicationOptions<'_>, MqttError> {
+    Ok(PublicationOptions {
+        retain,
+        message_expiry_interval: None,
+        topic: TopicReference::Name(topic_name(topic)?),
+        qos,
+    })
+}
+
+/// Convert a validated topic string into a `rust-mqtt` topic name
+fn topic_name(topic: &str) -> Result<TopicName<'_>, MqttError> {
+    if !is_valid_topic_name(topic) {
+        error!("Invalid MQTT topic name: '{}'", topic);
+        return Err(MqttError::ProtocolError);
+    }
+
+    let topic_string = MqttString::new(topic.into()).map_err(|e| {
+        error!("Failed to create MQTT topic string: {:?}", Debug2Format(&e));
+        MqttError::ProtocolError
+    })?;
+
+    // SAFETY: is_valid_topic_name() has checked that the topic string:
+    // 1. Is not empty
+    // 2. Does not contain wildcard characters (+, #)
+    // 3. Does not contain null characters
+    // Therefore, it's safe to use new_unchecked() here.
+    Ok(unsafe { TopicName::new_unchecked(topic_string) })
+}
+
+/// Convert a validated filter string into a `rust-mqtt` topic filter
+fn topic_filter(filter: &str) -> Result<TopicFilter<'_>, MqttError> {
+    if !inbound::is_valid_topic_filter(filter) {
+        error!("Invalid MQTT topic filter: '{}'", filter);
+        return Err(MqttError::ProtocolError);
+    }
+
+    let filter_string = MqttString::new(filter.into()).map_err(|e| {
+        error!(
+            "Failed to create MQTT filter string: {:?}",
+            Debug2Format(&e)
+        );
+        MqttError::ProtocolError
+    })?;
+
+    // SAFETY: is_valid_topic_filter() has checked the MQTT v5.0 §4.7.1 rules
+    // for wildcard placement, emptiness and null characters.
+    Ok(unsafe { TopicFilter::new_unchecked(filter_string) })
+}
+
+/// Check whether a string is a valid MQTT topic name (not a filter)
+///
+/// Topic names must be non-empty and must not contain wildcards (`+`, `#`)
+/// or null characters (MQTT v5.0 §4.7).
+fn is_valid_topic_name(topic: &str) -> bool {
+    !topic.is_empty() && !topic.contains(['+', '#', '\0'])
+}
+
+/// Build a per-device topic for this device
+///
+/// Returns `device/{client_id}/{subtopic}` using the device's MQTT client ID.
+pub fn device_topic(subtopic: &str) -> Result<String<MAX_TOPIC_LEN>, MqttError> {
+    format_mqtt_topic(device_id::mqtt_client_id().as_str(), subtopic)
+}
+
+/// Build a per-device topic filter for this device
+///
+/// Returns `device/{client_id}/{subtopic_filter}`; the suffix may contain
+/// wildcards, e.g. `cmd/#`.
+pub fn device_topic_filter(subtopic_filter: &str) -> Result<String<MAX_TOPIC_LEN>, MqttError> {
+    let client_id = device_id::mqtt_client_id();
+    let mut filter = format_mqtt_topic(client_id.as_str(), "")?;
+    filter
+        .push_str(subtopic_filter)
+        .map_err(|_| MqttError::BufferError)?;
+
+    if !inbound::is_valid_topic_filter(filter.as_str()) {
+        return Err(MqttError::ProtocolError);
+    }
+    Ok(filter)
+}
+
+/// Format an MQTT topic for telemetry data
+///
+/// Returns a topic string in the format `device/{id}/telemetry` where
+/// `{id}` is the device's MQTT client ID.
+///
+/// # Arguments
+///
+/// * `client_id` - The device's MQTT client ID
+/// * `subtopic` - The topic suffix (e.g., "telemetry", "status")
+///
+/// # Returns
+///
+/// Returns a heapless String with the formatted topic, or an error if
+/// the topic is too long to fit in the buffer.
+///
+/// # Example
+///
+/// ```no_run
+/// let client_id = device_id::mqtt_client_id();
+/// let topic = format_mqtt_topic(&client_id, "telemetry")?;
+/// // Result: "device/stm32f405-0123456789abcdef01234567/telemetry"
+/// ```
+fn format_mqtt_topic(client_id: &str, subtopic: &str) -> Result<String<MAX_TOPIC_LEN>, MqttError> {
+    // Validate that client_id and subtopic don't contain invalid MQTT topic characters
+    // MQTT spec: Topic names cannot contain wildcards (+, #) or null characters
+    if client_id.contains('+') || client_id.contains('#') || client_id.contains('\0') {
+        error!("Client ID contains invalid MQTT topic characters");
+        return Err(MqttError::ProtocolError);
+    }
+    if subtopic.contains('+') || subtopic.contains('#') || subtopic.contains('\0') {
+        error!("Subtopic contains invalid MQTT topic characters");
+        return Err(MqttError::ProtocolError);
+    }
+
+    let mut topic = String::<MAX_TOPIC_LEN>::new();
+
+    // Build: device/{client_id}/{subtopic}
+    topic
+        .push_str("device/")
+        .map_err(|_| MqttError::BufferError)?;
+    topic
+        .push_str(client_id)
+        .map_err(|_| MqttError::BufferError)?;
+    topic.push('/').map_err(|_| MqttError::BufferError)?;
+    topic
+        .push_str(subtopic)
+        .map_err(|_| MqttError::BufferError)?;
+
+    Ok(topic)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_config() {
+        let config = MqttConfig::default();
+        assert_eq!(config.broker_host, "192.168.1.1");
+        assert_eq!(config.broker_port, 8883);
+        assert_eq!(config.keep_alive_secs, 60);
+        assert!(config.clean_start);
+        assert_eq!(config.session_expiry_secs, 0);
+        assert!(config.will.is_none());
+        assert!(!config.birth_message);
+        assert!(config.username.is_none());
+        assert!(config.password.is_none());
+        assert!(config.ca_certificates.is_empty());
+        assert!(config.pins.is_empty());
+        assert!(config.client_identity.is_none());
+        assert!(config.psk.is_none());
+        assert_eq!(config.cipher_suite, CipherSuite::Aes128GcmSha256);
+        assert_eq!(config.max_fragment_length, tls_buffers::FRAGMENT_LENGTH);
+        assert!(config.alpn_protocols.is_empty());
+        assert!(config.fallback_port.is_none());
+        assert!(config.tcp.read_timeout_ms.is_none());
+        assert_eq!(config.handshake_timeout_ms, 15_000);
+        assert_eq!(config.reconnect.initial_ms, 1_000);
+        assert_eq!(config.reconnect.max_ms, 60_000);
+    }
+
+    #[test]
+    fn test_format_mqtt_topic() {
+        // Test telemetry topic
+        let topic = format_mqtt_topic("stm32f405-test123", "telemetry").unwrap();
+        assert_eq!(topic.as_str(), "device/stm32f405-test123/telemetry");
+
+        // Test status topic
+        let topic = format_mqtt_topic("stm32f405-test123", "status").unwrap();
+        assert_eq!(topic.as_str(), "device/stm32f405-test123/status");
+
+        // Test that topic length is within bounds
+        assert!(topic.len() < MAX_TOPIC_LEN);
+    }
+
+    #[test]
+    fn test_format_mqtt_topic_buffer_overflow() {
+        // Create a long client ID that should cause buffer overflow
+        // Use a fixed-size stack array instead of heap allocation
+        let long_id = "this_is_a_very_long_client_id_that_exceeds_the_maximum_allowed_topic_length_for_mqtt_messages";
+        let result = format_mqtt_topic(long_id, "telemetry");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_format_mqtt_topic_invalid_characters() {
+        // Test that wildcard characters are rejected
+        let result = format_mqtt_topic("client+wildcard", "telemetry");
+        assert!(result.is_err());
+
+        let result = format_mqtt_topic("client#wildcard", "telemetry");
+        assert!(result.is_err());
+
+        let result = format_mqtt_topic("valid-client", "status+wildcard");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_offline_status_will() {
+        let will = WillConfig::offline_status(5);
+        assert_eq!(will.subtopic, STATUS_SUBTOPIC);
+        assert_eq!(will.payload, OFFLINE_PAYLOAD);
+        assert!(will.retain);
+        assert_eq!(will.delay_secs, 5);
+    }
+
+    #[test]
+    fn test_is_valid_topic_name() {
+        assert!(is_valid_topic_name("device/stm32f405-test123/telemetry"));
+        assert!(is_valid_topic_name("a"));
+
+        assert!(!is_valid_topic_name(""));
+        assert!(!is_valid_topic_name("device/+/telemetry"));
+        assert!(!is_valid_topic_name("device/#"));
+        assert!(!is_valid_topic_name("device/\0"));
+    }
+}
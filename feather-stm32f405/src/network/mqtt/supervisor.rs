@@ -0,0 +1,512 @@
+#![deny(unsafe_code)]
+#![deny(warnings)]
+//! MQTT connection supervisor (SR-NET-003)
+//!
+//! Keeps an `MqttSession` alive for the lifetime of the firmware:
+//! - Publishes messages queued by other tasks through an `rtic-sync` channel
+//! - Tracks QoS 1/2 messages until acknowledged and retransmits them with the
+//!   DUP flag after a reconnect (see `inflight`)
+//! - Reads inbound packets as they arrive and dispatches PUBLISH messages to
+//!   the registered subscriptions (SR-NET-013), without blocking publishing
+//! - Sends PINGREQ when idle for half the keep-alive interval
+//! - Treats publish failures, missing PINGRESPs and TCP resets as a lost session
+//! - Reconnects with jittered exponential back-off using the hardware RNG
+//! - Announces the device with a retained "online" birth message after every
+//!   CONNACK; the broker-side will reports "offline" when the session is lost
+//!
+//! The current connection state is published through a `Watch` so any RTIC task
+//! can await it. `rtic-sync` only offers single-reader signals, so this is one of
+//! the places where `embassy-sync` is used for inter-task communication (ADR-001).
+//!
+//! # Example
+//!
+//! ```no_run
+//! // In a producer task
+//! let msg = OutboundMessage::new(topic.as_str(), payload, QoS::AtMostOnce, false)?;
+//! outbound_tx.try_send(msg).ok();
+//!
+//! // Wait for the PUBACK of a QoS 1 message
+//! static DELIVERY: DeliverySignal = DeliverySignal::new();
+//! let msg = OutboundMessage::new(topic.as_str(), payload, QoS::AtLeastOnce, false)?;
+//! supervisor::publish_confirmed(&mut outbound_tx, msg, &DELIVERY).await?;
+//!
+//! // In another task
+//! supervisor::wait_until_connected().await;
+//! ```
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use defmt::{debug, error, info, warn, Format};
+use embassy_futures::select::{select3, Either3};
+use embassy_net::Stack;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::watch::{Receiver, Watch};
+use embassy_time::{Duration, Instant, Timer};
+use heapless::{String, Vec};
+use rust_mqtt::types::QoS;
+
+use super::inbound::Subscriptions;
+use super::inflight::{self, AckOutcome, DeliveryResult, DeliverySignal, InflightTable};
+use super::{
+    MqttClient, MqttConfig, MqttSession, MqttStorage, SessionEvent, MAX_TOPIC_LEN, ONLINE_PAYLOAD,
+    STATUS_SUBTOPIC,
+};
+use crate::network::backoff::Backoff;
+use crate::network::error::{MqttError, NetworkError};
+
+/// Maximum payload size of a queued outbound message
+pub const MAX_PAYLOAD_LEN: usize = 256;
+
+/// Depth of the outbound message channel
+pub const OUTBOUND_QUEUE_DEPTH: usize = 4;
+
+/// Maximum number of tasks awaiting connection state changes
+pub const MAX_STATE_OBSERVERS: usize = 4;
+
+/// Time allowed for the broker to answer a PINGREQ
+const PINGRESP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Sending half of the outbound message channel
+pub type OutboundSender =
+    rtic_sync::channel::Sender<'static, OutboundMessage, OUTBOUND_QUEUE_DEPTH>;
+
+/// Receiving half of the outbound message channel (owned by the supervisor)
+pub type OutboundReceiver =
+    rtic_sync::channel::Receiver<'static, OutboundMessage, OUTBOUND_QUEUE_DEPTH>;
+
+/// Receiver for connection state changes
+pub type ConnectionStateReceiver =
+    Receiver<'static, CriticalSectionRawMutex, ConnectionState, MAX_STATE_OBSERVERS>;
+
+/// MQTT connection state as seen by the supervisor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+pub enum ConnectionState {
+    /// No session; waiting for the back-off delay to expire
+    Disconnected,
+    /// DNS, TCP, TLS and MQTT CONNECT in progress
+    Connecting,
+    /// CONNACK received; messages are being published
+    Connected,
+}
+
+/// Connection state observable shared with other tasks
+static CONNECTION_STATE: Watch<CriticalSectionRawMutex, ConnectionState, MAX_STATE_OBSERVERS> =
+    Watch::new_with(ConnectionState::Disconnected);
+
+/// Current connection state (non-blocking)
+pub fn connection_state() -> ConnectionState {
+    CONNECTION_STATE
+        .try_get()
+        .unwrap_or(ConnectionState::Disconnected)
+}
+
+/// Subscribe to connection state changes
+///
+/// Returns `None` if `MAX_STATE_OBSERVERS` receivers already exist.
+pub fn connection_state_receiver() -> Option<ConnectionStateReceiver> {
+    CONNECTION_STATE.receiver()
+}
+
+/// Wait until the supervisor reports `ConnectionState::Connected`
+///
+/// Holds one observer slot while waiting; falls back to polling if all
+/// `MAX_STATE_OBSERVERS` slots are taken.
+pub async fn wait_until_connected() {
+    match CONNECTION_STATE.receiver() {
+        Some(mut receiver) => {
+            receiver
+                .get_and(|state| *state == ConnectionState::Connected)
+                .await;
+        }
+        None => {
+            while connection_state() != ConnectionState::Connected {
+                Timer::after(Duration::from_millis(100)).await;
+            }
+        }
+    }
+}
+
+fn set_state(state: ConnectionState) {
+    if CONNECTION_STATE.try_get() != Some(state) {
+        info!("MQTT connection state: {:?}", state);
+        CONNECTION_STATE.sender().send(state);
+    }
+}
+
+/// Message queued for publication by the supervisor
+#[derive(Clone)]
+pub struct OutboundMessage {
+    /// Topic name (no wildcards)
+    pub topic: String<MAX_TOPIC_LEN>,
+    /// Message payload
+    pub payload: Vec<u8, MAX_PAYLOAD_LEN>,
+    /// Quality of Service level
+    pub qos: QoS,
+    /// Retain flag
+    pub retain: bool,
+    /// Signalled when the QoS flow completes (see `publish_confirmed`)
+    pub delivery: Option<&'static DeliverySignal>,
+    /// Tag reported through `delivery` to identify this message
+    pub delivery_tag: u32,
+}
+
+impl OutboundMessage {
+    /// Build a message, copying topic and payload into fixed-size storage
+    ///
+    /// # Errors
+    ///
+    /// Returns `MqttError::BufferError` if the topic or payload is too long.
+    pub fn new(topic: &str, payload: &[u8], qos: QoS, retain: bool) -> Result<Self, MqttError> {
+        let mut topic_buf = String::new();
+        topic_buf
+            .push_str(topic)
+            .map_err(|_| MqttError::BufferError)?;
+        let payload = Vec::from_slice(payload).map_err(|_| MqttError::BufferError)?;
+
+        Ok(Self {
+            topic: topic_buf,
+            payload,
+            qos,
+            retain,
+            delivery: None,
+            delivery_tag: 0,
+        })
+    }
+
+    /// Ask to be signalled with `tag` once the message has been acknowledged
+    pub fn with_delivery(mut self, delivery: &'static DeliverySignal, tag: u32) -> Self {
+        self.delivery = Some(delivery);
+        self.delivery_tag = tag;
+        self
+    }
+}
+
+/// Tag for the next confirmed publish
+static NEXT_DELIVERY_TAG: AtomicU32 = AtomicU32::new(0);
+
+/// Queue a message and wait until its QoS flow completes
+///
+/// Resolves when the PUBLISH has been written (QoS 0), acknowledged with
+/// PUBACK (QoS 1) or completed with PUBCOMP (QoS 2). Messages still in flight
+/// when the connection drops are retransmitted after the reconnect, so this
+/// may wait across reconnects; wrap it in a timeout if that is not wanted.
+///
+/// `delivery` must not be shared by concurrent calls, but may be reused once
+/// a call has returned or been cancelled (e.g. by a timeout): each message
+/// gets a fresh tag and reports for earlier messages are ignored.
+///
+/// # Errors
+///
+/// Returns `MqttError::ConnectionFailed` if the supervisor is gone and
+/// `MqttError::PublishFailed` if the broker rejected the message.
+pub async fn publish_confirmed(
+    outbound: &mut OutboundSender,
+    message: OutboundMessage,
+    delivery: &'static DeliverySignal,
+) -> DeliveryResult {
+    let tag = NEXT_DELIVERY_TAG.fetch_add(1, Ordering::Relaxed);
+    delivery.reset();
+    outbound
+        .send(message.with_delivery(delivery, tag))
+        .await
+        .map_err(|_| MqttError::ConnectionFailed)?;
+
+    loop {
+        let report = delivery.wait().await;
+        if report.tag == tag {
+            return report.result;
+        }
+        debug!("Ignoring late delivery report for message {}", report.tag);
+    }
+}
+
+/// Supervises a single MQTT broker connection
+pub struct MqttSupervisor {
+    client: MqttClient,
+}
+
+impl MqttSupervisor {
+    /// Create a supervisor for the given broker configuration
+    pub fn new(config: MqttConfig) -> Self {
+        Self {
+            client: MqttClient::new(config),
+        }
+    }
+
+    /// Run the connect → serve → back-off loop forever
+    ///
+    /// # Arguments
+    ///
+    /// * `stack` - Embassy network stack for DNS and TCP operations
+    /// * `rng` - Hardware RNG, used for TLS and back-off jitter
+    /// * `storage` - Packet and TCP buffers reused across reconnects
+    /// * `outbound` - Messages to publish while connected
+    /// * `subscriptions` - Topic filters to (re)subscribe after every CONNACK
+    pub async fn run<RNG>(
+        &self,
+        stack: &Stack<'static>,
+        rng: &mut RNG,
+        storage: &mut MqttStorage,
+        outbound: &mut OutboundReceiver,
+        subscriptions: &mut Subscriptions,
+    ) -> !
+    where
+        RNG: rand_core::RngCore + rand_core::CryptoRng,
+    {
+        let mut backoff = Backoff::new(self.client.config().reconnect);
+        let mut inflight = InflightTable::new();
+
+        loop {
+            set_state(ConnectionState::Connecting);
+
+            let mut buffers = storage.buffers();
+            match self.client.connect(stack, rng, &mut buffers).await {
+                Ok(mut session) => {
+                    backoff.reset();
+                    set_state(ConnectionState::Connected);
+
+                    let reason = self
+                        .serve(&mut session, outbound, subscriptions, &mut inflight)
+                        .await;
+                    error!("MQTT session lost: {:?}", reason);
+                }
+                Err(e) => warn!("MQTT connect failed: {:?}", e),
+            }
+
+            set_state(ConnectionState::Disconnected);
+
+            let delay = backoff.next_delay(rng);
+            warn!(
+                "Reconnecting to MQTT broker in {} ms (attempt {})",
+                delay.as_millis(),
+                backoff.attempt()
+            );
+            Timer::after(delay).await;
+        }
+    }
+
+    /// Run the event loop for one session until it fails
+    ///
+    /// Sends the registered subscriptions and resumes unacknowledged messages
+    /// from earlier sessions, then concurrently (publishing the birth message
+    /// and queued messages only once resumed flows have completed):
+    /// - publishes queued outbound messages while the in-flight table has room
+    /// - reads inbound packets, dispatches PUBLISH messages and feeds
+    ///   acknowledgements into the in-flight table
+    /// - sends PINGREQ when idle and enforces the PINGRESP deadline
+    ///
+    /// Only returns on failure, with the error that ended the session.
+    async fn serve(
+        &self,
+        session: &mut MqttSession<'_>,
+        outbound: &mut OutboundReceiver,
+        subscriptions: &mut Subscriptions,
+        inflight: &mut InflightTable,
+    ) -> NetworkError {
+        for subscription in subscriptions.iter() {
+            if let Err(e) = session
+                .subscribe(subscription.filter.as_str(), subscription.qos)
+                .await
+            {
+                return e;
+            }
+        }
+
+        // Each session starts a new rust-mqtt packet ID allocator, which
+        // knows nothing of the identifiers resumed below. New PUBLISHes wait
+        // until those flows have completed so no identifier is used twice.
+        let mut resuming = session.session_present() && !inflight.is_empty();
+        if let Err(e) = resume_inflight(session, inflight).await {
+            return e;
+        }
+        let mut birth_pending = self.client.config().birth_message;
+
+        let keep_alive_secs = self.client.config().keep_alive_secs;
+        let mut last_activity = Instant::now();
+        let mut pingresp_deadline: Option<Instant> = None;
+        let mut outbound_open = true;
+
+        loop {
+            if resuming && inflight.is_empty() {
+                info!("Resumed messages completed");
+                resuming = false;
+            }
+            if birth_pending && !resuming {
+                if let Err(e) = publish_birth(session, inflight).await {
+                    return e;
+                }
+                birth_pending = false;
+            }
+
+            let timer_deadline = match pingresp_deadline {
+                Some(deadline) => deadline,
+                None if keep_alive_secs == 0 => Instant::MAX,
+                None => last_activity + Duration::from_secs(u64::from(keep_alive_secs) / 2),
+            };
+
+            // A full in-flight table or a pending resume holds messages back
+            // in the channel
+            let accept_outbound = outbound_open && !resuming && !inflight.is_full();
+            let next_outbound = async {
+                if accept_outbound {
+                    outbound.recv().await
+                } else {
+                    core::future::pending().await
+                }
+            };
+
+            let event = select3(
+                next_outbound,
+                session.poll_header(),
+                Timer::at(timer_deadline),
+            )
+            .await;
+
+            let result = match event {
+                Either3::First(Ok(msg)) => {
+                    match session
+                        .publish(msg.topic.as_str(), &msg.payload, msg.qos, msg.retain)
+                        .await
+                    {
+                        Ok(packet_id) => {
+                            last_activity = Instant::now();
+                            // A duplicate packet ID means the session state is
+                            // inconsistent; the producer is told either way
+                            inflight.insert(packet_id, msg).map_err(NetworkError::from)
+                        }
+                        Err(e) => {
+                            inflight::complete(&msg, Err(MqttError::PublishFailed));
+                            Err(e)
+                        }
+                    }
+                }
+                Either3::First(Err(_)) => {
+                    // All senders dropped: keep serving inbound traffic and keep-alive
+                    warn!("Outbound MQTT channel closed");
+                    outbound_open = false;
+                    Ok(())
+                }
+                Either3::Second(Ok(header)) => match session.read_event(header).await {
+                    Ok(SessionEvent::Message(message)) => {
+                        info!(
+                            "Inbound message on '{}' ({} bytes)",
+                            message.topic.as_str(),
+                            message.payload.len()
+                        );
+                        subscriptions.dispatch(&message);
+                        Ok(())
+                    }
+                    Ok(SessionEvent::Pingresp) => {
+                        pingresp_deadline = None;
+                        Ok(())
+                    }
+                    Ok(SessionEvent::Ack(kind, packet_id)) => {
+                        if inflight.acknowledge(kind, packet_id) == AckOutcome::Unexpected {
+                            warn!("Unexpected {:?} for packet {}", kind, packet_id);
+                        }
+                        Ok(())
+                    }
+                    Ok(SessionEvent::Rejected(packet_id)) => {
+                        inflight.reject(packet_id);
+                        Ok(())
+                    }
+                    Ok(_) => Ok(()),
+                    Err(e) => Err(e),
+                },
+                Either3::Second(Err(e)) => Err(e),
+                Either3::Third(()) if pingresp_deadline.is_some() => {
+                    warn!("No PINGRESP within {} ms", PINGRESP_TIMEOUT.as_millis());
+                    Err(NetworkError::Timeout)
+                }
+                Either3::Third(()) => {
+                    let sent = session.send_ping().await;
+                    if sent.is_ok() {
+                        last_activity = Instant::now();
+                        pingresp_deadline = Some(last_activity + PINGRESP_TIMEOUT);
+                    }
+                    sent
+                }
+            };
+
+            if let Err(e) = result {
+                return e;
+            }
+        }
+    }
+}
+
+/// Resume messages left unacknowledged by a previous session
+///
+/// If the broker kept the session, PUBLISHes not yet acknowledged are
+/// retransmitted with the DUP flag and QoS 2 messages that already got their
+/// PUBREC have their PUBREL resent, both with the original packet identifier.
+/// Without a session the broker knows none of them: QoS 2 messages past
+/// PUBREC are complete (see `InflightTable::forget_session`) and the rest are
+/// published again as new messages under new identifiers.
+///
+/// On error the messages not yet sent stay in the table, so the next
+/// reconnect retries them.
+async fn resume_inflight(
+    session: &mut MqttSession<'_>,
+    inflight: &mut InflightTable,
+) -> Result<(), NetworkError> {
+    if !session.session_present() {
+        inflight.forget_session();
+    }
+    if inflight.is_empty() {
+        return Ok(());
+    }
+
+    info!("Resuming {} in-flight message(s)", inflight.len());
+    for index in 0..inflight.len() {
+        let entry = &inflight.entries()[index];
+        let message = &entry.message;
+        if entry.stale {
+            let packet_id = session
+                .publish(
+                    message.topic.as_str(),
+                    &message.payload,
+                    message.qos,
+                    message.retain,
+                )
+                .await?;
+            inflight.renumber(index, packet_id)?;
+        } else if entry.state.needs_republish() {
+            session
+                .republish(
+                    entry.packet_id,
+                    message.topic.as_str(),
+                    &message.payload,
+                    message.qos,
+                    message.retain,
+                )
+                .await?;
+        } else {
+            session.release(entry.packet_id).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Publish the retained "online" birth message on `device/{id}/status`
+///
+/// Sent at QoS 1 and tracked like any other message, so it is retransmitted
+/// if the connection drops before the PUBACK.
+async fn publish_birth(
+    session: &mut MqttSession<'_>,
+    inflight: &mut InflightTable,
+) -> Result<(), NetworkError> {
+    let topic = session.device_topic(STATUS_SUBTOPIC)?;
+    let message = OutboundMessage::new(topic.as_str(), ONLINE_PAYLOAD, QoS::AtLeastOnce, true)?;
+
+    let packet_id = session
+        .publish(topic.as_str(), ONLINE_PAYLOAD, message.qos, message.retain)
+        .await?;
+    info!("Birth message published on '{}'", topic.as_str());
+
+    match inflight.insert(packet_id, message) {
+        // A full table only means the birth message is not retransmitted
+        Ok(()) | Err(MqttError::BufferError) => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
@@ -0,0 +1,573 @@
+#![deny(unsafe_code)]
+#![deny(warnings)]
+//! NTPv4 packet format and on-wire calculations (RFC 5905)
+//!
+//! Pure functions used by `SntpClient`. Nothing here touches the network or
+//! the clocks, so the math is tested on the host.
+//!
+//! # On-Wire Protocol
+//!
+//! ```text
+//! client                 server
+//!   T1 ──── request ────► T2
+//!   T4 ◄──── reply ────── T3
+//!
+//! offset θ = ((T2 − T1) + (T3 − T4)) / 2
+//! delay  δ = (T4 − T1) − (T3 − T2)
+//! ```
+//!
+//! T1 is sent in the request's transmit field and the server echoes it in
+//! the reply's origin field. A reply whose origin does not match the request
+//! in flight is stale or spoofed and is dropped (RFC 5905 §8).
+//!
+//! # Kiss-o'-Death
+//!
+//! A server refusing service answers with stratum 0 and a four-character
+//! kiss code in the reference ID. Only kisses that echo our origin timestamp
+//! are honoured, so a spoofed packet cannot silence a server.
+//!
+//! # Era Handling
+//!
+//! NTP seconds wrap in 2036. Only differences between timestamps are used,
+//! computed modulo 2^64, which stay correct as long as the two clocks are
+//! less than 68 years apart. Before the first sync the local clock counts
+//! from the Unix epoch, still well within that range.
+
+use defmt::Format;
+
+use super::error::{KissCode, SntpError};
+
+/// Size of an NTP packet without extension fields
+pub const PACKET_LEN: usize = 48;
+
+/// Seconds from the NTP epoch (1900-01-01) to the Unix epoch (1970-01-01)
+pub const NTP_UNIX_OFFSET: u64 = 2_208_988_800;
+
+/// Protocol version sent in requests
+const VERSION: u8 = 4;
+
+/// Mode of a client request
+const MODE_CLIENT: u8 = 3;
+
+/// Mode of a server reply
+const MODE_SERVER: u8 = 4;
+
+/// Leap indicator of an unsynchronised server
+const LEAP_UNSYNCHRONIZED: u8 = 3;
+
+/// 64-bit NTP timestamp: seconds since 1900 (upper 32 bits) and fraction
+/// in units of 2^-32 s (lower 32 bits)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+pub struct NtpTimestamp(pub u64);
+
+impl NtpTimestamp {
+    /// Convert microseconds since the Unix epoch
+    pub fn from_unix_micros(micros: u64) -> Self {
+        let secs = (micros / 1_000_000 + NTP_UNIX_OFFSET) & 0xFFFF_FFFF;
+        let frac = ((micros % 1_000_000) << 32) / 1_000_000;
+        Self((secs << 32) | frac)
+    }
+
+    /// Whether the timestamp is unset
+    pub const fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Signed difference `self − earlier` in units of 2^-32 s
+    fn since(self, earlier: Self) -> i128 {
+        i128::from(self.0.wrapping_sub(earlier.0) as i64)
+    }
+
+    fn read(bytes: &[u8]) -> Self {
+        let mut raw = [0u8; 8];
+        raw.copy_from_slice(&bytes[..8]);
+        Self(u64::from_be_bytes(raw))
+    }
+}
+
+/// Fields of a server reply used by the client
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+pub struct Reply {
+    /// Leap indicator (3 = clock not synchronised)
+    pub leap: u8,
+    /// Protocol version
+    pub version: u8,
+    /// Association mode (4 = server)
+    pub mode: u8,
+    /// Distance from the reference clock (0 = Kiss-o'-Death)
+    pub stratum: u8,
+    /// Round-trip delay from the server to its reference clock in µs
+    pub root_delay_us: i64,
+    /// Maximum error of the server clock relative to its reference in µs
+    pub root_dispersion_us: i64,
+    /// Reference clock identifier (kiss code when stratum is 0)
+    pub reference_id: [u8; 4],
+    /// Client transmit time echoed by the server (T1)
+    pub origin: NtpTimestamp,
+    /// Server receive time (T2)
+    pub receive: NtpTimestamp,
+    /// Server transmit time (T3)
+    pub transmit: NtpTimestamp,
+}
+
+impl Reply {
+    /// Parse the fixed header of a reply
+    ///
+    /// # Errors
+    ///
+    /// Returns `SntpError::ParseError` if `packet` is shorter than
+    /// `PACKET_LEN`.
+    pub fn parse(packet: &[u8]) -> Result<Self, SntpError> {
+        if packet.len() < PACKET_LEN {
+            return Err(SntpError::ParseError);
+        }
+
+        Ok(Self {
+            leap: packet[0] >> 6,
+            version: (packet[0] >> 3) & 0x07,
+            mode: packet[0] & 0x07,
+            stratum: packet[1],
+            root_delay_us: short_to_micros(&packet[4..8]),
+            root_dispersion_us: short_to_micros(&packet[8..12]),
+            reference_id: [packet[12], packet[13], packet[14], packet[15]],
+            origin: NtpTimestamp::read(&packet[24..]),
+            receive: NtpTimestamp::read(&packet[32..]),
+            transmit: NtpTimestamp::read(&packet[40..]),
+        })
+    }
+}
+
+/// Offset and delay measured by one exchange
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+pub struct Sample {
+    /// Server clock minus local clock (θ) in microseconds
+    pub offset_us: i64,
+    /// Round-trip delay without server processing time (δ) in microseconds
+    pub delay_us: i64,
+    /// Server stratum
+    pub stratum: u8,
+    /// Server's root delay in microseconds
+    pub root_delay_us: i64,
+    /// Server's root dispersion in microseconds
+    pub root_dispersion_us: i64,
+}
+
+impl Sample {
+    /// Bound on the error of this sample's offset (root distance λ without
+    /// the dispersion growth terms, RFC 5905 §11.2)
+    pub fn root_distance_us(&self) -> i64 {
+        (self.root_delay_us + self.delay_us) / 2 + self.root_dispersion_us
+    }
+}
+
+/// Best sample of one server after the clock filter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+pub struct Candidate {
+    /// Lowest-delay sample of the server
+    pub sample: Sample,
+    /// RMS spread of the server's other samples around it in microseconds
+    pub jitter_us: i64,
+}
+
+/// Quality of a clock update chosen by `select`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+pub struct SyncQuality {
+    /// Index of the chosen candidate
+    pub source: usize,
+    /// Offset applied to the local clock in microseconds
+    pub offset_us: i64,
+    /// Round-trip delay to the chosen server in microseconds
+    pub delay_us: i64,
+    /// Root distance of the chosen server (error bound) in microseconds
+    pub root_distance_us: i64,
+    /// Combined server and selection jitter in microseconds
+    pub jitter_us: i64,
+    /// Servers whose intervals agree with the majority
+    pub truechimers: u8,
+    /// Servers rejected as falsetickers
+    pub falsetickers: u8,
+}
+
+/// Build a client request carrying `t1` in the transmit field
+pub fn request(t1: NtpTimestamp) -> [u8; PACKET_LEN] {
+    let mut packet = [0u8; PACKET_LEN];
+    // LI = 0, VN = 4, Mode = 3 (client)
+    packet[0] = (VERSION << 3) | MODE_CLIENT;
+    packet[40..48].copy_from_slice(&t1.0.to_be_bytes());
+    packet
+}
+
+/// Validate `reply` to the request sent at `t1` and compute offset and delay
+///
+/// `t4` is the local time the reply arrived. Both local timestamps must come
+/// from the same clock. Stratum limits are left to the caller.
+///
+/// # Errors
+///
+/// - `SntpError::InvalidMode` if the packet is not a server reply
+/// - `SntpError::UnsupportedVersion` for versions other than 3 and 4
+/// - `SntpError::OriginMismatch` if the reply does not echo `t1`
+/// - `SntpError::KissOfDeath` if the server sent a kiss code
+/// - `SntpError::Unsynchronized` if the server clock is not synchronised
+/// - `SntpError::ZeroTransmitTime` if the server sent no transmit time
+pub fn sample(reply: &Reply, t1: NtpTimestamp, t4: NtpTimestamp) -> Result<Sample, SntpError> {
+    if reply.mode != MODE_SERVER {
+        return Err(SntpError::InvalidMode);
+    }
+    if !(3..=VERSION).contains(&reply.version) {
+        return Err(SntpError::UnsupportedVersion);
+    }
+    if reply.origin != t1 {
+        return Err(SntpError::OriginMismatch);
+    }
+    // Checked before the leap indicator: kisses are sent unsynchronised
+    if reply.stratum == 0 {
+        return Err(SntpError::KissOfDeath(kiss_code(reply.reference_id)));
+    }
+    if reply.leap == LEAP_UNSYNCHRONIZED {
+        return Err(SntpError::Unsynchronized);
+    }
+    if reply.transmit.is_zero() {
+        return Err(SntpError::ZeroTransmitTime);
+    }
+
+    let outbound = reply.receive.since(t1);
+    let inbound = reply.transmit.since(t4);
+    let offset = (outbound + inbound) / 2;
+    // Rounding and clock granularity can make δ slightly negative
+    let delay = (t4.since(t1) - reply.transmit.since(reply.receive)).max(0);
+
+    Ok(Sample {
+        offset_us: to_micros(offset),
+        delay_us: to_micros(delay),
+        stratum: reply.stratum,
+        root_delay_us: reply.root_delay_us,
+        root_dispersion_us: reply.root_dispersion_us,
+    })
+}
+
+/// Pick the lowest-delay sample of one server (clock filter, RFC 5905 §10)
+///
+/// Delay is the best indicator of an undisturbed exchange: queueing only
+/// ever adds delay and skews the offset with it. Returns `None` without
+/// samples.
+pub fn clock_filter(samples: &[Sample]) -> Option<Candidate> {
+    let best = *samples.iter().min_by_key(|s| s.delay_us)?;
+    let jitter_us = rms(samples.iter().map(|s| s.offset_us - best.offset_us));
+    Some(Candidate {
+        sample: best,
+        jitter_us,
+    })
+}
+
+/// Reject falsetickers and choose the lowest-delay truechimer
+///
+/// Each candidate claims the true time lies within its offset ± root
+/// distance. The intersection algorithm (RFC 5905 §11.2.1) looks for the
+/// smallest number of falsetickers for which the remaining majority of
+/// intervals still overlap. Candidates outside that overlap are dropped, and
+/// the survivor with the lowest delay sets the clock.
+///
+/// # Errors
+///
+/// Returns `SntpError::NoMajority` if no majority of candidates agree
+/// (including when there are none).
+pub fn select(candidates: &[Candidate]) -> Result<SyncQuality, SntpError> {
+    let (low, high) = intersection(candidates).ok_or(SntpError::NoMajority)?;
+
+    let truechimers = || {
+        candidates.iter().enumerate().filter(move |(_, c)| {
+            let distance = c.sample.root_distance_us();
+            c.sample.offset_us - distance <= high && c.sample.offset_us + distance >= low
+        })
+    };
+    let (source, best) = truechimers()
+        .min_by_key(|(_, c)| c.sample.delay_us)
+        .ok_or(SntpError::NoMajority)?;
+    let count = truechimers().count();
+
+    // Spread of the other survivors around the chosen one, combined with
+    // the chosen server's own jitter (RFC 5905 §11.3)
+    let selection_jitter = rms(truechimers()
+        .filter(|&(index, _)| index != source)
+        .map(|(_, c)| c.sample.offset_us - best.sample.offset_us));
+    let jitter_us = (best.jitter_us.unsigned_abs().pow(2) + selection_jitter.unsigned_abs().pow(2))
+        .isqrt() as i64;
+
+    Ok(SyncQuality {
+        source,
+        offset_us: best.sample.offset_us,
+        delay_us: best.sample.delay_us,
+        root_distance_us: best.sample.root_distance_us(),
+        jitter_us,
+        truechimers: count as u8,
+        falsetickers: (candidates.len() - count) as u8,
+    })
+}
+
+/// Largest interval shared by a majority of candidates, as `(low, high)`
+fn intersection(candidates: &[Candidate]) -> Option<(i64, i64)> {
+    /// Upper bound on candidates taken into account
+    const MAX: usize = 16;
+
+    let n = candidates.len().min(MAX);
+    // (offset, +1 for a lower / -1 for an upper endpoint)
+    let mut endpoints = [(0i64, 0i8); 2 * MAX];
+    for (i, c) in candidates.iter().take(n).enumerate() {
+        let distance = c.sample.root_distance_us();
+        endpoints[2 * i] = (c.sample.offset_us - distance, 1);
+        endpoints[2 * i + 1] = (c.sample.offset_us + distance, -1);
+    }
+    let endpoints = &mut endpoints[..2 * n];
+    // Lower endpoints first on ties, so touching intervals overlap
+    endpoints.sort_unstable_by_key(|&(value, kind)| (value, -kind));
+
+    // Allow ever more falsetickers while the rest is still a majority
+    for falsetickers in 0..n.div_ceil(2) {
+        let needed = (n - falsetickers) as i32;
+
+        let mut count = 0;
+        let low = endpoints.iter().find_map(|&(value, kind)| {
+            count += i32::from(kind);
+            (count >= needed).then_some(value)
+        });
+
+        let mut count = 0;
+        let high = endpoints.iter().rev().find_map(|&(value, kind)| {
+            count -= i32::from(kind);
+            (count >= needed).then_some(value)
+        });
+
+        if let (Some(low), Some(high)) = (low, high) {
+            if low <= high {
+                return Some((low, high));
+            }
+        }
+    }
+    None
+}
+
+/// Root mean square of `values`, 0 if empty
+fn rms(values: impl Iterator<Item = i64>) -> i64 {
+    let (sum, count) = values.fold((0u64, 0u64), |(sum, count), v| {
+        let v = v.unsigned_abs();
+        (sum.saturating_add(v.saturating_mul(v)), count + 1)
+    });
+    sum.checked_div(count).unwrap_or(0).isqrt() as i64
+}
+
+/// Convert an NTP short-format value (16.16 fixed point) to microseconds
+fn short_to_micros(bytes: &[u8]) -> i64 {
+    let raw = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    ((u64::from(raw) * 1_000_000) >> 16) as i64
+}
+
+/// Decode the kiss code carried in the reference ID of a stratum 0 reply
+pub fn kiss_code(reference_id: [u8; 4]) -> KissCode {
+    match &reference_id {
+        b"RATE" => KissCode::Rate,
+        b"DENY" => KissCode::Deny,
+        b"RSTR" => KissCode::Rstr,
+        _ => KissCode::Other(reference_id),
+    }
+}
+
+/// Convert 2^-32 s units to microseconds
+fn to_micros(units: i128) -> i64 {
+    ((units * 1_000_000) >> 32) as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2024-01-01 00:00:00 UTC
+    const BASE_US: u64 = 1_704_067_200_000_000;
+
+    fn ts(unix_micros: u64) -> NtpTimestamp {
+        NtpTimestamp::from_unix_micros(unix_micros)
+    }
+
+    fn reply(t1: NtpTimestamp, t2: NtpTimestamp, t3: NtpTimestamp) -> [u8; PACKET_LEN] {
+        let mut packet = [0u8; PACKET_LEN];
+        packet[0] = (VERSION << 3) | MODE_SERVER;
+        packet[1] = 2;
+        packet[24..32].copy_from_slice(&t1.0.to_be_bytes());
+        packet[32..40].copy_from_slice(&t2.0.to_be_bytes());
+        packet[40..48].copy_from_slice(&t3.0.to_be_bytes());
+        packet
+    }
+
+    #[test]
+    fn test_request_carries_t1() {
+        let t1 = ts(BASE_US);
+        let packet = request(t1);
+        assert_eq!(packet[0], 0x23);
+        assert_eq!(NtpTimestamp::read(&packet[40..]), t1);
+    }
+
+    #[test]
+    fn test_offset_and_delay() {
+        // Server is 2 s ahead; 10 ms each way, 1 ms processing
+        let t1 = ts(BASE_US);
+        let t2 = ts(BASE_US + 2_000_000 + 10_000);
+        let t3 = ts(BASE_US + 2_000_000 + 11_000);
+        let t4 = ts(BASE_US + 21_000);
+
+        let reply = Reply::parse(&reply(t1, t2, t3)).unwrap();
+        let sample = sample(&reply, t1, t4).unwrap();
+        assert!((sample.offset_us - 2_000_000).abs() <= 1);
+        assert!((sample.delay_us - 20_000).abs() <= 1);
+        assert_eq!(sample.stratum, 2);
+    }
+
+    #[test]
+    fn test_offset_from_unsynced_local_clock() {
+        // Local clock still counts from the Unix epoch (uptime only)
+        let t1 = ts(5_000_000);
+        let t2 = ts(BASE_US + 5_000);
+        let t3 = ts(BASE_US + 5_000);
+        let t4 = ts(5_010_000);
+
+        let reply = Reply::parse(&reply(t1, t2, t3)).unwrap();
+        let sample = sample(&reply, t1, t4).unwrap();
+        let expected = (BASE_US - 5_000_000) as i64;
+        assert!((sample.offset_us - expected).abs() <= 1);
+        assert!((sample.delay_us - 10_000).abs() <= 1);
+    }
+
+    #[test]
+    fn test_origin_must_match_request() {
+        let t1 = ts(BASE_US);
+        let stale = ts(BASE_US - 1_000_000);
+        let reply = Reply::parse(&reply(stale, t1, t1)).unwrap();
+        assert_eq!(sample(&reply, t1, t1), Err(SntpError::OriginMismatch));
+    }
+
+    #[test]
+    fn test_reply_validation() {
+        let t1 = ts(BASE_US);
+        let valid = reply(t1, t1, t1);
+
+        let mut unsynced = valid;
+        unsynced[0] |= LEAP_UNSYNCHRONIZED << 6;
+        let r = Reply::parse(&unsynced).unwrap();
+        assert_eq!(sample(&r, t1, t1), Err(SntpError::Unsynchronized));
+
+        let mut client_mode = valid;
+        client_mode[0] = (VERSION << 3) | MODE_CLIENT;
+        let r = Reply::parse(&client_mode).unwrap();
+        assert_eq!(sample(&r, t1, t1), Err(SntpError::InvalidMode));
+
+        let mut old_version = valid;
+        old_version[0] = (2 << 3) | MODE_SERVER;
+        let r = Reply::parse(&old_version).unwrap();
+        assert_eq!(sample(&r, t1, t1), Err(SntpError::UnsupportedVersion));
+
+        let mut no_transmit = valid;
+        no_transmit[40..48].fill(0);
+        let r = Reply::parse(&no_transmit).unwrap();
+        assert_eq!(sample(&r, t1, t1), Err(SntpError::ZeroTransmitTime));
+    }
+
+    #[test]
+    fn test_kiss_of_death() {
+        let t1 = ts(BASE_US);
+        let mut kiss = reply(t1, NtpTimestamp(0), NtpTimestamp(0));
+        kiss[0] |= LEAP_UNSYNCHRONIZED << 6;
+        kiss[1] = 0;
+        kiss[12..16].copy_from_slice(b"RATE");
+
+        let r = Reply::parse(&kiss).unwrap();
+        assert_eq!(
+            sample(&r, t1, t1),
+            Err(SntpError::KissOfDeath(KissCode::Rate))
+        );
+
+        // A kiss that does not echo our request is ignored as spoofed
+        assert_eq!(
+            sample(&r, ts(BASE_US + 1), t1),
+            Err(SntpError::OriginMismatch)
+        );
+    }
+
+    #[test]
+    fn test_kiss_codes() {
+        assert_eq!(kiss_code(*b"DENY"), KissCode::Deny);
+        assert_eq!(kiss_code(*b"RSTR"), KissCode::Rstr);
+        assert_eq!(kiss_code(*b"INIT"), KissCode::Other(*b"INIT"));
+    }
+
+    fn candidate(offset_us: i64, delay_us: i64, root_dispersion_us: i64) -> Candidate {
+        Candidate {
+            sample: Sample {
+                offset_us,
+                delay_us,
+                stratum: 2,
+                root_delay_us: 0,
+                root_dispersion_us,
+            },
+            jitter_us: 0,
+        }
+    }
+
+    #[test]
+    fn test_root_delay_and_dispersion() {
+        let t1 = ts(BASE_US);
+        let mut packet = reply(t1, t1, t1);
+        // 0.5 s root delay, 0.25 s root dispersion (16.16 fixed point)
+        packet[4..8].copy_from_slice(&0x0000_8000u32.to_be_bytes());
+        packet[8..12].copy_from_slice(&0x0000_4000u32.to_be_bytes());
+
+        let r = Reply::parse(&packet).unwrap();
+        let sample = sample(&r, t1, t1).unwrap();
+        assert_eq!(sample.root_delay_us, 500_000);
+        assert_eq!(sample.root_dispersion_us, 250_000);
+        assert_eq!(sample.root_distance_us(), 500_000);
+    }
+
+    #[test]
+    fn test_clock_filter_prefers_lowest_delay() {
+        let samples = [
+            candidate(1_300, 9_000, 0).sample,
+            candidate(1_000, 4_000, 0).sample,
+            candidate(700, 6_000, 0).sample,
+        ];
+        let best = clock_filter(&samples).unwrap();
+        assert_eq!(best.sample.offset_us, 1_000);
+        // RMS of (300, 0, -300)
+        assert_eq!(best.jitter_us, 244);
+        assert!(clock_filter(&[]).is_none());
+    }
+
+    #[test]
+    fn test_select_rejects_falseticker() {
+        let candidates = [
+            candidate(1_000, 20_000, 5_000),
+            candidate(3_000_000, 1_000, 5_000),
+            candidate(2_000, 10_000, 5_000),
+        ];
+        let quality = select(&candidates).unwrap();
+        assert_eq!(quality.source, 2);
+        assert_eq!(quality.offset_us, 2_000);
+        assert_eq!(quality.truechimers, 2);
+        assert_eq!(quality.falsetickers, 1);
+        assert_eq!(quality.jitter_us, 1_000);
+    }
+
+    #[test]
+    fn test_select_needs_majority() {
+        let split = [candidate(0, 1_000, 100), candidate(1_000_000, 1_000, 100)];
+        assert_eq!(select(&split), Err(SntpError::NoMajority));
+        assert_eq!(select(&[]), Err(SntpError::NoMajority));
+
+        let single = select(&[candidate(42, 1_000, 100)]).unwrap();
+        assert_eq!(single.offset_us, 42);
+        assert_eq!(single.falsetickers, 0);
+    }
+
+    #[test]
+    fn test_short_packet() {
+        assert_eq!(Reply::parse(&[0u8; 47]), Err(SntpError::ParseError));
+    }
+}
@@ -1,22 +1,43 @@
 #![deny(unsafe_code)]
 #![deny(warnings)]
 //! SNTP client implementing NetworkClient trait
+//!
+//! Each query is a full NTPv4 client/server exchange (see `ntp`): the reply
+//! must echo our transmit timestamp, and the clock offset is computed from
+//! all four timestamps rather than the server time plus half the RTT.
+//!
+//! Several servers are sampled per sync and combined as in RFC 5905 §10-11:
+//! falsetickers are voted out, so one wrong pool member cannot set the RTC.
+//!
+//! After the initial sync, `SntpClient::resync` keeps the clock in step:
+//! polls start at `min_poll_interval_ms` and stretch to `poll_interval_ms`
+//! (15 minutes by default) while offsets stay small, shrink again when the
+//! clock drifts, and back off exponentially while no server answers.
+//!
+//! Each sync also feeds the drift estimators (`time::DriftEstimator`) that
+//! correct the frequency of the wall clock and, via RTC_CALR, of the RTC.
+//!
+//! Kiss-o'-Death replies are honoured per server: DENY and RSTR stop all
+//! further queries to that server, RATE doubles its minimum query spacing
+//! (64 s up to 36 h) and the client moves on to the next server meanwhile.
 
-use defmt::{error, info, warn, Debug2Format};
-use embassy_net::dns::DnsQueryType;
+use defmt::{debug, error, info, warn, Debug2Format, Format};
 use embassy_net::udp::{PacketMetadata, UdpSocket};
-use embassy_net::{IpEndpoint, Stack};
+use embassy_net::{IpAddress, IpEndpoint, Stack};
 use embassy_time::{Duration, Instant, Timer};
-use rtic_monotonics::fugit::ExtU64;
+use heapless::Vec;
 use rtic_monotonics::Monotonic;
 
 use crate::ccmram;
-use crate::time::{write_rtc, RtcError, Timestamp};
+use crate::time::{calibrate_rtc, rtc_offset, write_rtc, DriftEstimator, RtcError, Timestamp};
 use crate::Mono;
 
+use super::backoff::backoff_ceiling_ms;
 use super::client::NetworkClient;
 use super::config::SntpConfig;
-use super::error::NetworkError;
+use super::dns;
+use super::error::{KissCode, NetworkError, SntpError};
+use super::ntp::{self, Candidate, NtpTimestamp, Reply, Sample, SyncQuality};
 
 /// SNTP/NTP port (UDP 123)
 const SNTP_PORT: u16 = 123;
@@ -30,80 +51,376 @@ impl From<RtcError> for NetworkError {
     }
 }
 
+/// Maximum number of servers whose Kiss-o'-Death state is tracked
+pub const MAX_SERVERS: usize = 4;
+
+/// Maximum number of samples kept per server
+pub const MAX_SAMPLES: usize = 8;
+
+/// Minimum spacing between samples from one server
+const SAMPLE_SPACING: Duration = Duration::from_secs(2);
+
+/// Longest wait for a held-off server before selecting without it
+const MAX_ROUND_WAIT: Duration = Duration::from_secs(16);
+
+/// Delay before the first retry of a failed query; doubles per failure
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Query spacing after the first RATE kiss (2^6 s, RFC 5905 MINPOLL)
+const RATE_MIN_INTERVAL: Duration = Duration::from_secs(64);
+
+/// Upper bound for the RATE hold-off (2^17 s, RFC 5905 MAXPOLL)
+const RATE_MAX_INTERVAL: Duration = Duration::from_secs(131_072);
+
+/// Kiss-o'-Death state of one server
+#[derive(Debug, Clone, Copy)]
+struct ServerState {
+    /// Server sent DENY or RSTR and must not be queried again
+    demobilized: bool,
+    /// Minimum spacing between queries requested through RATE
+    min_interval: Duration,
+    /// Earliest time the next query may be sent
+    next_query: Instant,
+}
+
+impl ServerState {
+    const fn new() -> Self {
+        Self {
+            demobilized: false,
+            min_interval: Duration::from_ticks(0),
+            next_query: Instant::from_ticks(0),
+        }
+    }
+
+    /// Record a query sent at `now`
+    fn on_query(&mut self, now: Instant) {
+        self.next_query = now + self.min_interval;
+    }
+
+    /// Do not query again before `until`
+    fn hold_off(&mut self, until: Instant) {
+        self.next_query = self.next_query.max(until);
+    }
+
+    /// Apply a kiss code received at `now`
+    fn on_kiss(&mut self, code: KissCode, now: Instant) {
+        match code {
+            KissCode::Deny | KissCode::Rstr => self.demobilized = true,
+            KissCode::Rate => {
+                self.min_interval =
+                    (self.min_interval * 2).clamp(RATE_MIN_INTERVAL, RATE_MAX_INTERVAL);
+                self.next_query = now + self.min_interval;
+            }
+            // Informational codes do not change the schedule
+            KissCode::Other(_) => {}
+        }
+    }
+}
+
+/// Adaptive resync schedule
+///
+/// The interval starts short, doubles while the clock stays within
+/// `max_offset_us` of the servers and halves when it does not, between
+/// `min_poll_interval_ms` and `poll_interval_ms`. Failed syncs are retried
+/// with exponential back-off instead.
+#[derive(Debug, Clone, Copy)]
+struct PollSchedule {
+    /// Current interval after a successful sync
+    interval_ms: u64,
+    /// Consecutive failed syncs
+    failures: u32,
+}
+
+impl PollSchedule {
+    fn new(config: &SntpConfig) -> Self {
+        Self {
+            interval_ms: config.min_poll_interval_ms,
+            failures: 0,
+        }
+    }
+
+    /// Adapt the interval to the offset corrected by a successful sync
+    fn on_success(&mut self, offset_us: i64, config: &SntpConfig) {
+        self.failures = 0;
+        let offset = offset_us.unsigned_abs();
+        self.interval_ms = if offset > config.max_offset_us {
+            self.interval_ms / 2
+        } else if offset < config.max_offset_us / 2 {
+            self.interval_ms.saturating_mul(2)
+        } else {
+            self.interval_ms
+        }
+        .clamp(config.min_poll_interval_ms, config.poll_interval_ms);
+    }
+
+    fn on_failure(&mut self) {
+        self.failures = self.failures.saturating_add(1);
+    }
+
+    /// Delay until the next sync
+    fn next_delay(&self, config: &SntpConfig) -> Duration {
+        let delay_ms = match self.failures {
+            0 => self.interval_ms,
+            failures => backoff_ceiling_ms(&config.backoff, failures - 1),
+        };
+        Duration::from_millis(delay_ms)
+    }
+}
+
+/// Outcome of a successful synchronisation
+#[derive(Debug, Clone, Copy, Format)]
+pub struct SyncResult {
+    /// Corrected time written to the RTC and wall clock
+    pub timestamp: Timestamp,
+    /// Offset, error bound and server agreement behind `timestamp`
+    pub quality: SyncQuality,
+}
+
 /// SNTP client for time synchronization
 pub struct SntpClient {
     config: SntpConfig,
+    servers: [ServerState; MAX_SERVERS],
+    schedule: PollSchedule,
+    /// Frequency error of the TIM2-based wall clock
+    wallclock_drift: DriftEstimator,
+    /// Frequency error of the LSE-driven RTC
+    rtc_drift: DriftEstimator,
 }
 
 impl SntpClient {
     /// Create a new SNTP client with default configuration
     pub fn new() -> Self {
-        Self {
-            config: SntpConfig::default(),
-        }
+        Self::with_config(SntpConfig::default())
     }
 
     /// Create a new SNTP client with custom configuration
     #[allow(dead_code)]
     pub fn with_config(config: SntpConfig) -> Self {
-        Self { config }
+        Self {
+            schedule: PollSchedule::new(&config),
+            config,
+            servers: [ServerState::new(); MAX_SERVERS],
+            wallclock_drift: DriftEstimator::new(),
+            rtc_drift: DriftEstimator::new(),
+        }
+    }
+
+    /// Keep the clock synchronised, never returning
+    ///
+    /// Waits out the current poll interval, resyncs and reschedules, so it
+    /// is meant to run next to the long-lived clients (e.g. with `select`)
+    /// after the initial `run`. Failures are logged and retried with
+    /// back-off; the clock keeps free-running from the last good sync.
+    pub async fn resync(&mut self, stack: &Stack<'static>) -> ! {
+        loop {
+            let delay = self.schedule.next_delay(&self.config);
+            info!("Next SNTP sync in {} s", delay.as_secs());
+            Timer::after(delay).await;
+
+            if let Err(e) = self.run(stack).await {
+                warn!(
+                    "SNTP resync failed ({} in a row): {:?}",
+                    self.schedule.failures, e
+                );
+            }
+        }
     }
 
     /// Perform SNTP synchronization with internal RTC update
-    async fn sync(&self, stack: &Stack<'static>) -> Result<Timestamp, NetworkError> {
+    ///
+    /// Every server is queried `samples_per_server` times, interleaved and
+    /// at least `SAMPLE_SPACING` apart per server. The clock filter keeps
+    /// each server's lowest-delay sample, the intersection algorithm drops
+    /// falsetickers, and the lowest-delay survivor sets the clock.
+    ///
+    /// Servers silenced by Kiss-o'-Death are skipped: permanently after DENY
+    /// or RSTR, until their RATE hold-off expires otherwise. A server that
+    /// fails is retried after a doubling delay, up to `retry_count` times.
+    async fn sync(&mut self, stack: &Stack<'static>) -> Result<SyncResult, NetworkError> {
         info!("Starting SNTP synchronization");
-        for server in self.config.servers {
-            for attempt in 0..self.config.retry_count {
-                info!(
-                    "Attempting SNTP sync with {} (attempt {})",
-                    server,
-                    attempt + 1
-                );
-                match self.sntp_request(stack, server).await {
-                    Ok(timestamp) => {
-                        info!(
-                            "SNTP sync successful: {}.{:06} UTC",
-                            timestamp.unix_secs, timestamp.micros
-                        );
-                        write_rtc(timestamp)?;
-                        self.calibrate_wallclock(timestamp);
-                        return Ok(timestamp);
-                    }
-                    Err(e) => {
-                        warn!("SNTP sync failed: {:?}, retrying...", e);
-                        Mono::delay(2000_u64.millis()).await;
+        if self.config.servers.len() > MAX_SERVERS {
+            warn!("Only the first {} SNTP servers are used", MAX_SERVERS);
+        }
+        let servers = &self.config.servers[..self.config.servers.len().min(MAX_SERVERS)];
+        let wanted = self.config.samples_per_server.clamp(1, MAX_SAMPLES);
+
+        let mut samples: [Vec<Sample, MAX_SAMPLES>; MAX_SERVERS] = Default::default();
+        let mut failures = [0usize; MAX_SERVERS];
+
+        loop {
+            let mut next_round: Option<Instant> = None;
+
+            for (index, server) in servers.iter().enumerate() {
+                if samples[index].len() >= wanted
+                    || failures[index] >= self.config.retry_count
+                    || self.servers[index].demobilized
+                {
+                    continue;
+                }
+
+                let now = Instant::now();
+                let state = &mut self.servers[index];
+                if now < state.next_query {
+                    debug!("{} is held off", server);
+                } else {
+                    state.on_query(now);
+                    debug!(
+                        "Sampling {} ({}/{})",
+                        server,
+                        samples[index].len() + 1,
+                        wanted
+                    );
+                    match self.sntp_request(stack, server).await {
+                        Ok(sample) => {
+                            // Cannot fail: at most `wanted` samples are taken
+                            let _ = samples[index].push(sample);
+                            self.servers[index].hold_off(now + SAMPLE_SPACING);
+                        }
+                        Err(NetworkError::Sntp(SntpError::KissOfDeath(code))) => {
+                            warn!("{} sent Kiss-o'-Death {}", server, code);
+                            self.servers[index].on_kiss(code, Instant::now());
+                        }
+                        Err(e) => {
+                            failures[index] += 1;
+                            let delay = RETRY_DELAY * (1u32 << failures[index].min(4));
+                            warn!(
+                                "SNTP query to {} failed: {:?}, retrying in {} ms",
+                                server,
+                                e,
+                                delay.as_millis()
+                            );
+                            self.servers[index].hold_off(Instant::now() + delay);
+                        }
                     }
                 }
+
+                let state = &self.servers[index];
+                if samples[index].len() < wanted
+                    && failures[index] < self.config.retry_count
+                    && !state.demobilized
+                {
+                    next_round =
+                        Some(next_round.map_or(state.next_query, |t| t.min(state.next_query)));
+                }
+            }
+
+            // Servers held off for longer (RATE) sit this sync out
+            match next_round {
+                Some(at) if at <= Instant::now() + MAX_ROUND_WAIT => Timer::at(at).await,
+                _ => break,
             }
         }
-        error!("All SNTP sync attempts failed");
-        Err(NetworkError::AllServersFailed)
+
+        // Clock filter per server, then source selection across servers
+        let mut candidates: Vec<Candidate, MAX_SERVERS> = Vec::new();
+        let mut sources: Vec<&str, MAX_SERVERS> = Vec::new();
+        for (server, samples) in servers.iter().zip(&samples) {
+            if let Some(candidate) = ntp::clock_filter(samples) {
+                // Cannot fail: one candidate per server
+                let _ = candidates.push(candidate);
+                let _ = sources.push(*server);
+            }
+        }
+        if candidates.is_empty() {
+            error!("All SNTP sync attempts failed");
+            return Err(NetworkError::AllServersFailed);
+        }
+
+        let quality = ntp::select(&candidates).map_err(|e| {
+            error!("SNTP servers disagree: {}", e);
+            e
+        })?;
+        info!(
+            "Selected {}: offset {} µs, delay {} µs, jitter {} µs, {} truechimer(s), {} falseticker(s)",
+            sources[quality.source],
+            quality.offset_us,
+            quality.delay_us,
+            quality.jitter_us,
+            quality.truechimers,
+            quality.falsetickers
+        );
+
+        let timestamp = self.apply_offset(quality.offset_us)?;
+        info!(
+            "SNTP sync successful: {}.{:06} UTC",
+            timestamp.unix_secs, timestamp.micros
+        );
+        Ok(SyncResult { timestamp, quality })
     }
 
-    fn calibrate_wallclock(&self, timestamp: Timestamp) {
-        let mono_micros = Mono::now().ticks() as u32;
-        ccmram::calibrate_wallclock(timestamp.unix_secs as u32, timestamp.micros, mono_micros);
+    /// Step the RTC and wall clock by `offset_us` and update their drift
+    ///
+    /// The corrected time is derived from a fresh local clock reading, so
+    /// the wall clock is calibrated against the monotonic time of that same
+    /// instant. Before the RTC is overwritten, its own error against that
+    /// time feeds the RTC drift estimate; once a baseline is long enough,
+    /// the result goes to the RTC smooth calibration register.
+    fn apply_offset(&mut self, offset_us: i64) -> Result<Timestamp, NetworkError> {
+        let mono_micros = Mono::now().ticks();
+        let unix_micros = local_micros(mono_micros).saturating_add_signed(offset_us);
+        let timestamp = Timestamp::new(unix_micros / 1_000_000, (unix_micros % 1_000_000) as u32);
+
+        if let Some(freq_ppb) = self.wallclock_drift.update(mono_micros, offset_us) {
+            info!("Wall-clock frequency correction: {} ppb", freq_ppb);
+        }
+        match rtc_offset(timestamp) {
+            Ok(rtc_offset_us) => {
+                debug!("RTC off by {} µs since last sync", rtc_offset_us);
+                if let Some(freq_ppb) = self.rtc_drift.update(mono_micros, rtc_offset_us) {
+                    calibrate_rtc(freq_ppb)?;
+                    info!("RTC smooth calibration: {} ppb", freq_ppb);
+                }
+            }
+            // Not written since boot (or unreadable): this write starts the
+            // baseline instead
+            Err(_) => self.rtc_drift.restart(mono_micros),
+        }
+
+        write_rtc(timestamp)?;
+        ccmram::calibrate_wallclock(
+            timestamp.unix_secs as u32,
+            timestamp.micros,
+            mono_micros,
+            self.wallclock_drift.frequency_ppb(),
+        );
         info!(
             "Wall-clock calibrated: RTC updated, mono={} µs",
             mono_micros
         );
+        Ok(timestamp)
     }
 
+    /// Query `server`, trying each of its addresses until one answers
     async fn sntp_request(
         &self,
         stack: &Stack<'static>,
         server: &str,
-    ) -> Result<Timestamp, NetworkError> {
-        let server_ip = stack
-            .dns_query(server, DnsQueryType::A)
-            .await
-            .map_err(|_| NetworkError::DnsError)?
-            .first()
-            .copied()
-            .ok_or(NetworkError::DnsError)?;
+    ) -> Result<Sample, NetworkError> {
+        let addresses = dns::resolve(stack, server).await?;
+        info!("Resolved {} to {} address(es)", server, addresses.len());
+
+        let mut last_error = NetworkError::DnsError;
+        for &server_ip in &addresses {
+            match self.query(stack, server_ip).await {
+                Ok(sample) => return Ok(sample),
+                // A kiss applies to the server, not just this address
+                Err(e @ NetworkError::Sntp(SntpError::KissOfDeath(_))) => return Err(e),
+                Err(e) => {
+                    warn!("SNTP query to {} failed: {:?}", Debug2Format(&server_ip), e);
+                    last_error = e;
+                }
+            }
+        }
+        Err(last_error)
+    }
 
+    /// Exchange one NTP packet with `server_ip`
+    async fn query(
+        &self,
+        stack: &Stack<'static>,
+        server_ip: IpAddress,
+    ) -> Result<Sample, NetworkError> {
         let server_endpoint = IpEndpoint::new(server_ip, SNTP_PORT);
-        info!("Resolved {} to {}", server, Debug2Format(&server_endpoint));
 
         let mut rx_meta = [PacketMetadata::EMPTY; 2];
         let mut rx_buffer = [0u8; 64];
@@ -118,17 +435,15 @@ impl SntpClient {
         );
         socket.bind(0).map_err(|_| NetworkError::SocketError)?;
 
-        // NTP request: LI=0, VN=3, Mode=3 (Client)
-        let mut ntp_packet = [0u8; 48];
-        ntp_packet[0] = 0x1B;
-        let transmit_time = Instant::now();
+        // T1 goes out in the transmit field and must come back as origin
+        let t1 = NtpTimestamp::from_unix_micros(local_micros(Mono::now().ticks()));
         socket
-            .send_to(&ntp_packet, server_endpoint)
+            .send_to(&ntp::request(t1), server_endpoint)
             .await
             .map_err(|_| NetworkError::SocketError)?;
         info!("Sent NTP request to {}", Debug2Format(&server_endpoint));
 
-        let mut response = [0u8; 48];
+        let mut response = [0u8; ntp::PACKET_LEN];
         let timeout_future = Timer::after(Duration::from_millis(self.config.timeout_ms));
         let recv_future = socket.recv_from(&mut response);
         let (recv_len, from_addr) =
@@ -138,7 +453,7 @@ impl SntpClient {
                     result.map_err(|_| NetworkError::SocketError)?
                 }
             };
-        let receive_time = Instant::now();
+        let t4 = NtpTimestamp::from_unix_micros(local_micros(Mono::now().ticks()));
 
         info!(
             "Received {} bytes from {}",
@@ -146,43 +461,42 @@ impl SntpClient {
             Debug2Format(&from_addr)
         );
 
-        if recv_len < 48 || from_addr.endpoint.addr != server_ip {
+        if from_addr.endpoint.addr != server_ip {
             return Err(NetworkError::InvalidResponse);
         }
 
-        let stratum = response[1];
-        info!("NTP server stratum: {}", stratum);
+        let reply = Reply::parse(&response[..recv_len])?;
+        info!("NTP server stratum: {}", reply.stratum);
 
-        if stratum == 0 || stratum > self.config.max_stratum {
+        let sample = ntp::sample(&reply, t1, t4).map_err(|e| {
+            warn!("Rejected NTP reply: {}", e);
+            e
+        })?;
+        if sample.stratum > self.config.max_stratum {
             warn!(
                 "Invalid stratum {} (max {})",
-                stratum, self.config.max_stratum
+                sample.stratum, self.config.max_stratum
             );
-            return Err(NetworkError::ServerError);
+            return Err(SntpError::InvalidStratum.into());
         }
-
-        let tx_timestamp_secs =
-            u32::from_be_bytes([response[40], response[41], response[42], response[43]]) as u64;
-        let tx_timestamp_frac =
-            u32::from_be_bytes([response[44], response[45], response[46], response[47]]);
-
-        let rtt = receive_time.duration_since(transmit_time);
-        let rtt_correction_micros = rtt.as_micros() / 2;
-
-        let mut timestamp = Timestamp::from_ntp(tx_timestamp_secs, tx_timestamp_frac);
-        timestamp.micros = timestamp
-            .micros
-            .saturating_add(rtt_correction_micros as u32);
-        if timestamp.micros >= 1_000_000 {
-            timestamp.unix_secs = timestamp.unix_secs.saturating_add(1);
-            timestamp.micros -= 1_000_000;
-        }
-
         info!(
-            "NTP timestamp: {}.{:06} UTC (RTT correction: {} µs)",
-            timestamp.unix_secs, timestamp.micros, rtt_correction_micros
+            "NTP offset {} µs, delay {} µs",
+            sample.offset_us, sample.delay_us
         );
-        Ok(timestamp)
+        Ok(sample)
+    }
+}
+
+/// Local clock in microseconds since the Unix epoch at `mono_micros`
+///
+/// This is the calibrated wall clock once time has been synchronised and
+/// plain uptime before that; offsets are measured against the same reading.
+fn local_micros(mono_micros: u64) -> u64 {
+    if ccmram::is_wallclock_calibrated() {
+        let (secs, micros) = ccmram::now_unix_time(mono_micros);
+        u64::from(secs) * 1_000_000 + u64::from(micros)
+    } else {
+        mono_micros
     }
 }
 
@@ -193,9 +507,106 @@ impl Default for SntpClient {
 }
 
 impl NetworkClient for SntpClient {
-    type Output = Timestamp;
+    type Output = SyncResult;
 
     async fn run(&mut self, stack: &Stack<'static>) -> Result<Self::Output, NetworkError> {
-        self.sync(stack).await
+        let result = self.sync(stack).await;
+        match &result {
+            Ok(sync) => self
+                .schedule
+                .on_success(sync.quality.offset_us, &self.config),
+            Err(_) => self.schedule.on_failure(),
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_kiss_doubles_interval() {
+        let mut state = ServerState::new();
+        let now = Instant::from_secs(1_000);
+
+        state.on_kiss(KissCode::Rate, now);
+        assert_eq!(state.min_interval, RATE_MIN_INTERVAL);
+        assert_eq!(state.next_query, now + RATE_MIN_INTERVAL);
+
+        state.on_kiss(KissCode::Rate, now);
+        assert_eq!(state.min_interval, RATE_MIN_INTERVAL * 2);
+
+        // Later queries keep the spacing the server asked for
+        state.on_query(now);
+        assert_eq!(state.next_query, now + RATE_MIN_INTERVAL * 2);
+    }
+
+    #[test]
+    fn test_rate_interval_is_capped() {
+        let mut state = ServerState::new();
+        for _ in 0..32 {
+            state.on_kiss(KissCode::Rate, Instant::from_ticks(0));
+        }
+        assert_eq!(state.min_interval, RATE_MAX_INTERVAL);
+    }
+
+    #[test]
+    fn test_deny_and_rstr_demobilize() {
+        for code in [KissCode::Deny, KissCode::Rstr] {
+            let mut state = ServerState::new();
+            state.on_kiss(code, Instant::from_ticks(0));
+            assert!(state.demobilized);
+        }
+
+        let mut state = ServerState::new();
+        state.on_kiss(KissCode::Other(*b"INIT"), Instant::from_ticks(0));
+        assert!(!state.demobilized);
+    }
+
+    #[test]
+    fn test_poll_interval_adapts_to_offset() {
+        let config = SntpConfig::default();
+        let mut schedule = PollSchedule::new(&config);
+        assert_eq!(schedule.next_delay(&config), Duration::from_secs(64));
+
+        // Small offsets stretch the interval up to the configured maximum
+        for _ in 0..8 {
+            schedule.on_success(1_000, &config);
+        }
+        assert_eq!(schedule.next_delay(&config), Duration::from_secs(900));
+
+        // A large offset halves it again
+        schedule.on_success(-200_000, &config);
+        assert_eq!(schedule.next_delay(&config), Duration::from_secs(450));
+
+        // Offsets between half and full threshold keep it
+        schedule.on_success(30_000, &config);
+        assert_eq!(schedule.next_delay(&config), Duration::from_secs(450));
+
+        // Never below the minimum
+        for _ in 0..8 {
+            schedule.on_success(i64::MIN, &config);
+        }
+        assert_eq!(schedule.next_delay(&config), Duration::from_secs(64));
+    }
+
+    #[test]
+    fn test_failures_back_off_until_success() {
+        let config = SntpConfig::default();
+        let mut schedule = PollSchedule::new(&config);
+        schedule.on_success(0, &config);
+
+        schedule.on_failure();
+        assert_eq!(schedule.next_delay(&config), Duration::from_secs(30));
+        schedule.on_failure();
+        assert_eq!(schedule.next_delay(&config), Duration::from_secs(60));
+        for _ in 0..10 {
+            schedule.on_failure();
+        }
+        assert_eq!(schedule.next_delay(&config), Duration::from_secs(900));
+
+        schedule.on_success(0, &config);
+        assert_eq!(schedule.next_delay(&config), Duration::from_secs(256));
     }
 }
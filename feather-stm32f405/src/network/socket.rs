@@ -4,11 +4,27 @@
 //!
 //! This module provides an async wrapper around `embassy_net::tcp::TcpSocket`
 //! that implements the `embedded-io-async` traits required by `embedded-tls`.
+//!
+//! # Timeouts
+//!
+//! Every blocking operation has a deadline from `TcpConfig`: connect, read
+//! and write (including flush) fail with `NetworkError::Timeout` when it
+//! passes, so a peer that stops responding cannot hang the calling task.
+//! TCP keep-alive probes detect a dead peer on an idle connection; smoltcp
+//! aborts the socket after three unanswered intervals.
+//!
+//! # Errors
+//!
+//! embassy-net errors keep their cause: a refused connect, a reset, a
+//! missing route and an unexpected end of stream each have their own
+//! `NetworkError` variant, so the reconnect log says why a link dropped.
 
-use embassy_net::tcp::TcpSocket;
+use embassy_net::tcp::{self, ConnectError, TcpSocket};
 use embassy_net::{IpEndpoint, Stack};
+use embassy_time::{with_timeout, Duration};
 use embedded_io_async::{ErrorType, Read, Write};
 
+use super::config::TcpConfig;
 use super::error::NetworkError;
 
 /// Async TCP socket wrapper implementing embedded-io-async traits
@@ -19,13 +35,16 @@ use super::error::NetworkError;
 /// # Example
 ///
 /// ```no_run
-/// let mut socket = AsyncTcpSocket::new(rx_buffer, tx_buffer);
-/// socket.connect(stack, endpoint).await?;
+/// let mut socket = AsyncTcpSocket::new(stack, rx_buffer, tx_buffer, TcpConfig::default());
+/// socket.connect(endpoint).await?;
 /// // Now socket can be used with embedded-tls
 /// ```
 #[allow(dead_code)] // Phase 1: Will be used when TLS is integrated
 pub struct AsyncTcpSocket<'a> {
     socket: TcpSocket<'a>,
+    connect_timeout: Duration,
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
 }
 
 impl<'a> AsyncTcpSocket<'a> {
@@ -36,18 +55,32 @@ impl<'a> AsyncTcpSocket<'a> {
     /// * `stack` - Embassy network stack
     /// * `rx_buffer` - Buffer for receiving data (typically 4-8 KB)
     /// * `tx_buffer` - Buffer for transmitting data (typically 4-8 KB)
+    /// * `config` - Timeouts and keep-alive interval
     ///
     /// # Example
     ///
     /// ```no_run
     /// let mut rx_buffer = [0u8; 4096];
     /// let mut tx_buffer = [0u8; 4096];
-    /// let socket = AsyncTcpSocket::new(stack, &mut rx_buffer, &mut tx_buffer);
+    /// let socket = AsyncTcpSocket::new(stack, &mut rx_buffer, &mut tx_buffer, TcpConfig::default());
     /// ```
     #[allow(dead_code)] // Phase 1: Will be used when TLS is integrated
-    pub fn new(stack: Stack<'a>, rx_buffer: &'a mut [u8], tx_buffer: &'a mut [u8]) -> Self {
+    pub fn new(
+        stack: Stack<'a>,
+        rx_buffer: &'a mut [u8],
+        tx_buffer: &'a mut [u8],
+        config: TcpConfig,
+    ) -> Self {
+        let mut socket = TcpSocket::new(stack, rx_buffer, tx_buffer);
+        let keep_alive = config.keep_alive_ms.map(Duration::from_millis);
+        socket.set_keep_alive(keep_alive);
+        socket.set_timeout(keep_alive.map(|interval| interval * 3));
+
         Self {
-            socket: TcpSocket::new(stack, rx_buffer, tx_buffer),
+            socket,
+            connect_timeout: Duration::from_millis(config.connect_timeout_ms),
+            read_timeout: config.read_timeout_ms.map(Duration::from_millis),
+            write_timeout: config.write_timeout_ms.map(Duration::from_millis),
         }
     }
 
@@ -59,13 +92,20 @@ impl<'a> AsyncTcpSocket<'a> {
     ///
     /// # Errors
     ///
-    /// Returns `NetworkError::SocketError` if connection fails
+    /// Returns `NetworkError::Timeout` if the server does not answer within
+    /// the connect timeout, `NetworkError::ConnectionRefused` if it rejects
+    /// the connection, `NetworkError::NoRoute` if it is unreachable and
+    /// `NetworkError::SocketError` if the socket is already in use
     #[allow(dead_code)] // Phase 1: Will be used when TLS is integrated
     pub async fn connect(&mut self, endpoint: IpEndpoint) -> Result<(), NetworkError> {
-        self.socket
-            .connect(endpoint)
-            .await
-            .map_err(|_| NetworkError::SocketError)
+        match with_timeout(self.connect_timeout, self.socket.connect(endpoint)).await {
+            Ok(result) => result.map_err(connect_error),
+            Err(_) => {
+                // Drop the half-open connection so the socket can be reused
+                self.socket.abort();
+                Err(NetworkError::Timeout)
+            }
+        }
     }
 
     /// Close the socket
@@ -94,10 +134,11 @@ impl ErrorType for AsyncTcpSocket<'_> {
 /// This allows `embedded-tls` to read data from the TCP socket.
 impl Read for AsyncTcpSocket<'_> {
     async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
-        self.socket
-            .read(buf)
-            .await
-            .map_err(|_| NetworkError::SocketError)
+        match deadline(self.read_timeout, self.socket.read(buf)).await? {
+            // The peer sent FIN: nothing more will arrive on this connection
+            Ok(0) if !buf.is_empty() => Err(NetworkError::Eof),
+            result => result.map_err(io_error),
+        }
     }
 }
 
@@ -106,16 +147,48 @@ impl Read for AsyncTcpSocket<'_> {
 /// This allows `embedded-tls` to write data to the TCP socket.
 impl Write for AsyncTcpSocket<'_> {
     async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
-        self.socket
-            .write(buf)
-            .await
-            .map_err(|_| NetworkError::SocketError)
+        deadline(self.write_timeout, self.socket.write(buf))
+            .await?
+            .map_err(io_error)
     }
 
     async fn flush(&mut self) -> Result<(), Self::Error> {
-        self.socket
-            .flush()
+        deadline(self.write_timeout, self.socket.flush())
+            .await?
+            .map_err(io_error)
+    }
+}
+
+/// Map an embassy-net connect error to `NetworkError`
+///
+/// smoltcp reports a RST in reply to the SYN as a reset; before the
+/// connection is established that means the server refused it.
+fn connect_error(e: ConnectError) -> NetworkError {
+    match e {
+        ConnectError::ConnectionReset => NetworkError::ConnectionRefused,
+        ConnectError::TimedOut => NetworkError::Timeout,
+        ConnectError::NoRoute => NetworkError::NoRoute,
+        ConnectError::InvalidState => NetworkError::SocketError,
+    }
+}
+
+/// Map an embassy-net read/write error to `NetworkError`
+fn io_error(e: tcp::Error) -> NetworkError {
+    match e {
+        tcp::Error::ConnectionReset => NetworkError::ConnectionReset,
+    }
+}
+
+/// Run `future` to completion, or fail with `NetworkError::Timeout` once
+/// `timeout` has elapsed (`None` = no deadline)
+async fn deadline<F: core::future::Future>(
+    timeout: Option<Duration>,
+    future: F,
+) -> Result<F::Output, NetworkError> {
+    match timeout {
+        Some(timeout) => with_timeout(timeout, future)
             .await
-            .map_err(|_| NetworkError::SocketError)
+            .map_err(|_| NetworkError::Timeout),
+        None => Ok(future.await),
     }
 }
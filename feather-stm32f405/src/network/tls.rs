@@ -1,74 +1,220 @@
+#![deny(unsafe_code)]
 #![deny(warnings)]
 //! TLS 1.3 client implementation using embedded-tls
 //!
 //! This module provides TLS 1.3 client functionality for secure MQTT communication.
 //! It uses the `embedded-tls` crate which is designed for no_std environments.
 //!
-//! # Safety Note
+//! Connection setup (DNS, TCP, handshake) lives in `connector::SecureConnector`;
+//! this module holds the shared `TlsClientConfig` and a handshake smoke test.
 //!
-//! This module uses `unsafe` code to access CCM RAM buffers for TLS operations.
-//! The unsafe code is isolated to buffer access and is carefully reviewed and documented.
-//! All other code follows safe Rust practices.
+//! # Certificate Verification
+//!
+//! With `verify_server: true` the server chain is checked against
+//! `ca_certificates` (DER) and/or the leaf against SHA-256 `pins` (see
+//! `verifier`). Verification is off by default so the public test broker
+//! works without provisioning a CA.
+//!
+//! # Client Authentication
+//!
+//! Setting `client_identity` enables mutual TLS: the device presents its
+//! X.509 certificate and signs the handshake with the matching ECDSA P-256
+//! key, as required by AWS IoT Core. Convert the key to unencrypted PKCS#8
+//! DER first:
+//!
+//! ```text
+//! openssl pkcs8 -topk8 -nocrypt -in device.key -outform DER -out device.key.der
+//! openssl x509 -in device.crt -outform DER -out device.der
+//! ```
+//!
+//! # Cipher Suites
+//!
+//! `cipher_suite` selects the single suite offered in the ClientHello:
+//! `TLS_AES_128_GCM_SHA256` (default) or `TLS_AES_256_GCM_SHA384`
+//! (SR-SEC-001/002). Both are compiled in; the choice is logged at handshake
+//! time and available from `TlsStream::cipher_suite`.
+//!
+//! # Record Size
+//!
+//! `max_fragment_length` is sent as the Max Fragment Length extension
+//! (RFC 6066) and must fit the static TLS buffers; it defaults to
+//! `tls_buffers::FRAGMENT_LENGTH`, which sizes them. The newer
+//! `record_size_limit` extension (RFC 8449) is not supported by embedded-tls.
+//!
+//! # ALPN and Port Fallback
+//!
+//! `alpn_protocols` is sent in the ClientHello (RFC 7301). AWS IoT Core
+//! serves MQTT on port 443 to clients offering `x-amzn-mqtt-ca`, so with
+//! `fallback_port: Some(443)` the same firmware works on networks that block
+//! 8883: a failed TCP connect on `server_port` is retried once on the
+//! fallback port. `TlsStream::remote_port` reports the port used and
+//! `TlsStream::offered_alpn` the protocols offered; embedded-tls does not
+//! expose which one the server selected. A connect timeout counts as a
+//! failed connect, so a port that silently drops SYNs falls back too.
+//!
+//! # Timeouts
+//!
+//! `tcp` bounds the TCP connect and every socket read and write, and enables
+//! TCP keep-alive; `handshake_timeout_ms` bounds the whole TLS handshake, so
+//! a server that accepts TCP and then stalls is reported as
+//! `NetworkError::Timeout` instead of hanging the network task.
+//!
+//! # Pre-Shared Keys
+//!
+//! Setting `psk` replaces certificates entirely: no CA bundle or device
+//! certificate is stored and the server sends no certificate chain. The
+//! handshake uses the TLS 1.3 `psk_dhe_ke` mode, so an ephemeral ECDHE key
+//! share still provides forward secrecy. The PSK-only `psk_ke` mode is not
+//! offered by embedded-tls (it always sends a key share). The connector logs
+//! the handshake duration together with the `AuthMode`, which is what we
+//! compare across modes.
 //!
 //! # Phase 1 Limitations
 //!
-//! - Certificate verification is disabled (set `verify_server: false`)
 //! - Single connection at a time (due to static CCM RAM buffers)
 //! - Test server: `broker.emqx.io:8883` (public MQTT broker with TLS support)
 //!
 //! # Memory Usage
 //!
-//! - TLS read buffer: 18 KB in main SRAM (see `src/tls_buffers.rs`)
-//! - TLS write buffer: 16 KB in main SRAM (see `src/tls_buffers.rs`)
+//! - TLS read buffer: 4.3 KB in main SRAM with 4 KB fragments (see `src/tls_buffers.rs`)
+//! - TLS write buffer: 4.3 KB in main SRAM with 4 KB fragments (see `src/tls_buffers.rs`)
 //! - TCP socket buffers: 8 KB in main SRAM (4 KB RX + 4 KB TX)
 
-#![allow(unsafe_code)] // Required for static TLS buffer access
-
-use defmt::{debug, error, info, warn, Debug2Format};
-use embassy_net::dns::DnsQueryType;
-use embassy_net::{IpEndpoint, Stack};
-use embedded_tls::{
-    Aes128GcmSha256, CryptoProvider, NoVerify, TlsConfig, TlsConnection, TlsContext, TlsVerifier,
-};
+use defmt::{error, info, Format};
+use embassy_net::Stack;
+use p256::ecdsa::{SigningKey, VerifyingKey};
+use p256::pkcs8::DecodePrivateKey;
 
+use super::config::TcpConfig;
+use super::connector::SecureConnector;
+use super::error::{NetworkError, TlsError};
+use super::verifier::Pin;
+use super::x509::Certificate;
 use crate::tls_buffers;
 
-use super::error::{NetworkError, TlsError};
-use super::socket::AsyncTcpSocket;
+/// Device certificate and private key for mutual TLS (SR-NET-002)
+#[derive(Clone, Copy)]
+pub struct ClientIdentity {
+    /// DER-encoded X.509 device certificate (ECDSA P-256)
+    pub certificate: &'static [u8],
+    /// DER-encoded unencrypted PKCS#8 ECDSA P-256 private key
+    pub private_key: &'static [u8],
+}
 
-/// Simple crypto provider that wraps an RNG for TLS operations
-struct SimpleCryptoProvider<RNG> {
-    rng: RNG,
-    verifier: NoVerify,
+impl ClientIdentity {
+    /// Parse the private key and check it belongs to the certificate
+    ///
+    /// Done before connecting so a mismatched pair is reported locally
+    /// instead of as an opaque handshake failure.
+    ///
+    /// # Errors
+    ///
+    /// Returns `TlsError::ClientIdentityError` if either part cannot be
+    /// parsed or the key does not match the certificate's public key.
+    pub fn signing_key(&self) -> Result<SigningKey, TlsError> {
+        let certificate = Certificate::from_der(self.certificate).map_err(|e| {
+            error!("Client certificate invalid: {:?}", e);
+            TlsError::ClientIdentityError
+        })?;
+        let key = SigningKey::from_pkcs8_der(self.private_key).map_err(|_| {
+            error!("Client private key is not a PKCS#8 P-256 key");
+            TlsError::ClientIdentityError
+        })?;
+
+        let public_key = VerifyingKey::from(&key).to_encoded_point(false);
+        if public_key.as_bytes() != certificate.public_key {
+            error!("Client private key does not match the certificate");
+            return Err(TlsError::ClientIdentityError);
+        }
+        Ok(key)
+    }
 }
 
-impl<RNG> SimpleCryptoProvider<RNG> {
-    fn new(rng: RNG) -> Self {
-        Self {
-            rng,
-            verifier: NoVerify,
+/// Minimum accepted PSK length in bytes (128 bits)
+pub const MIN_PSK_LEN: usize = 16;
+
+/// External pre-shared key for TLS 1.3 PSK authentication
+#[derive(Clone, Copy)]
+pub struct PskCredentials {
+    /// PSK identity sent in the ClientHello `pre_shared_key` extension
+    pub identity: &'static [u8],
+    /// Shared secret, at least `MIN_PSK_LEN` bytes (32 recommended)
+    pub key: &'static [u8],
+}
+
+impl PskCredentials {
+    /// Check the identity is present and the key long enough
+    ///
+    /// # Errors
+    ///
+    /// Returns `TlsError::ClientIdentityError` otherwise.
+    pub fn validate(&self) -> Result<(), TlsError> {
+        if self.identity.is_empty() || self.key.len() < MIN_PSK_LEN {
+            error!(
+                "Invalid PSK: identity {} bytes, key {} bytes (min {})",
+                self.identity.len(),
+                self.key.len(),
+                MIN_PSK_LEN
+            );
+            return Err(TlsError::ClientIdentityError);
         }
+        Ok(())
     }
 }
 
-impl<RNG> CryptoProvider for SimpleCryptoProvider<RNG>
-where
-    RNG: rand_core::CryptoRngCore,
-{
-    type CipherSuite = Aes128GcmSha256;
-    type Signature = &'static [u8];
+/// TLS 1.3 cipher suite offered to the server
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+pub enum CipherSuite {
+    /// `TLS_AES_128_GCM_SHA256` (mandatory-to-implement, fastest)
+    Aes128GcmSha256,
+    /// `TLS_AES_256_GCM_SHA384` (256-bit keys for higher assurance)
+    Aes256GcmSha384,
+}
 
-    fn rng(&mut self) -> impl rand_core::CryptoRngCore {
-        &mut self.rng
+impl Default for CipherSuite {
+    fn default() -> Self {
+        Self::Aes128GcmSha256
     }
+}
 
-    fn verifier(
-        &mut self,
-    ) -> Result<&mut impl TlsVerifier<Self::CipherSuite>, embedded_tls::TlsError> {
-        Ok(&mut self.verifier)
+/// Max Fragment Length values defined by RFC 6066
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+pub enum FragmentLength {
+    /// 2^9 bytes
+    Bytes512,
+    /// 2^10 bytes
+    Bytes1024,
+    /// 2^11 bytes
+    Bytes2048,
+    /// 2^12 bytes
+    Bytes4096,
+}
+
+impl FragmentLength {
+    /// Plaintext bytes per record
+    pub const fn bytes(self) -> usize {
+        match self {
+            Self::Bytes512 => 512,
+            Self::Bytes1024 => 1024,
+            Self::Bytes2048 => 2048,
+            Self::Bytes4096 => 4096,
+        }
     }
 }
 
+/// How a handshake authenticates the peers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+pub enum AuthMode {
+    /// Server certificate not verified, no client authentication
+    Unverified,
+    /// Server certificate verified against the CA bundle
+    ServerCertificate,
+    /// Client certificate presented (mutual TLS)
+    MutualCertificate,
+    /// Pre-shared key with ephemeral ECDHE (`psk_dhe_ke`)
+    PskDhe,
+}
+
 /// TLS client configuration
 #[derive(Clone, Copy)]
 #[allow(dead_code)] // Phase 1: Will be used when TLS is integrated
@@ -77,8 +223,43 @@ pub struct TlsClientConfig {
     pub server_name: &'static str,
     /// Server port (typically 8883 for MQTTS)
     pub server_port: u16,
-    /// Enable certificate verification (false for Phase 1 testing)
+    /// Enable certificate verification against `ca_certificates` and `pins`
     pub verify_server: bool,
+    /// Trusted CA certificates (DER) for server verification
+    pub ca_certificates: &'static [&'static [u8]],
+    /// Accepted SHA-256 pins of the server certificate or public key
+    pub pins: &'static [Pin],
+    /// Client certificate and key for mutual TLS (`None` = no client auth)
+    pub client_identity: Option<ClientIdentity>,
+    /// Pre-shared key; when set, certificate settings are ignored
+    pub psk: Option<PskCredentials>,
+    /// Cipher suite offered in the ClientHello
+    pub cipher_suite: CipherSuite,
+    /// Max Fragment Length to negotiate (`None` = standard 16 KB records)
+    pub max_fragment_length: Option<FragmentLength>,
+    /// ALPN protocol names offered in the ClientHello (empty = no ALPN)
+    pub alpn_protocols: &'static [&'static [u8]],
+    /// Port to retry on when the TCP connect to `server_port` fails
+    pub fallback_port: Option<u16>,
+    /// TCP connect/read/write timeouts and keep-alive
+    pub tcp: TcpConfig,
+    /// Deadline for the TLS handshake in milliseconds
+    pub handshake_timeout_ms: u64,
+}
+
+impl TlsClientConfig {
+    /// Authentication mode the handshake will use
+    pub fn auth_mode(&self) -> AuthMode {
+        if self.psk.is_some() {
+            AuthMode::PskDhe
+        } else if self.client_identity.is_some() {
+            AuthMode::MutualCertificate
+        } else if self.verify_server {
+            AuthMode::ServerCertificate
+        } else {
+            AuthMode::Unverified
+        }
+    }
 }
 
 impl Default for TlsClientConfig {
@@ -87,6 +268,16 @@ impl Default for TlsClientConfig {
             server_name: "broker.emqx.io",
             server_port: 8883,
             verify_server: false, // Phase 1: skip cert verification
+            ca_certificates: &[],
+            pins: &[],
+            client_identity: None,
+            psk: None,
+            cipher_suite: CipherSuite::default(),
+            max_fragment_length: tls_buffers::FRAGMENT_LENGTH,
+            alpn_protocols: &[],
+            fallback_port: None,
+            tcp: TcpConfig::default(),
+            handshake_timeout_ms: 15_000,
         }
     }
 }
@@ -110,6 +301,16 @@ impl TlsClient {
     ///     server_name: "broker.emqx.io",
     ///     server_port: 8883,
     ///     verify_server: false,
+    ///     ca_certificates: &[],
+    ///     pins: &[],
+    ///     client_identity: None,
+    ///     psk: None,
+    ///     cipher_suite: CipherSuite::Aes128GcmSha256,
+    ///     max_fragment_length: Some(FragmentLength::Bytes4096),
+    ///     alpn_protocols: &[],
+    ///     fallback_port: None,
+    ///     tcp: TcpConfig::default(),
+    ///     handshake_timeout_ms: 15_000,
     /// };
     /// let client = TlsClient::new(config);
     /// ```
@@ -129,8 +330,8 @@ impl TlsClient {
     /// # Phase 1 Limitation
     ///
     /// This is a test function that establishes the connection and immediately closes it.
-    /// It demonstrates that TLS 1.3 handshake works but doesn't return a usable connection.
-    /// A future version will manage buffers differently to allow returning connections.
+    /// It demonstrates that TLS 1.3 handshake works; use `SecureConnector` directly
+    /// to keep the connection open.
     ///
     /// # Arguments
     ///
@@ -141,11 +342,6 @@ impl TlsClient {
     ///
     /// `Ok(())` if handshake succeeds, or a `NetworkError` if any step fails.
     ///
-    /// # Safety
-    ///
-    /// This function uses unsafe code to access static CCM RAM buffers.
-    /// These buffers must only be used by a single TLS connection at a time.
-    ///
     /// # Example
     ///
     /// ```no_run
@@ -166,78 +362,16 @@ impl TlsClient {
             self.config.server_name, self.config.server_port
         );
 
-        // Step 1: DNS resolution
-        let server_ip = stack
-            .dns_query(self.config.server_name, DnsQueryType::A)
-            .await
-            .map_err(|e| {
-                error!("DNS query failed: {:?}", Debug2Format(&e));
-                NetworkError::DnsError
-            })?
-            .first()
-            .copied()
-            .ok_or_else(|| {
-                error!("DNS returned no results for {}", self.config.server_name);
-                NetworkError::DnsError
-            })?;
-
-        let endpoint = IpEndpoint::new(server_ip, self.config.server_port);
-        info!(
-            "Resolved {} to {}",
-            self.config.server_name,
-            Debug2Format(&endpoint)
-        );
-
-        // Step 2: Allocate TCP socket buffers (in main SRAM, not CCM)
+        // Socket buffers live on this task's stack for the duration of the test
         let mut rx_buffer = [0u8; 4096];
         let mut tx_buffer = [0u8; 4096];
 
-        // Step 3: Create and connect TCP socket
-        let mut socket = AsyncTcpSocket::new(*stack, &mut rx_buffer, &mut tx_buffer);
-        socket.connect(endpoint).await?;
-        info!("TCP connection established to {}", Debug2Format(&endpoint));
-
-        // Step 4: Get TLS buffers from main SRAM (unsafe - single use only)
-        // SAFETY: These static buffers are only used by one TLS connection at a time.
-        // The buffers are obtained once and used for the duration of this function.
-        let (read_buf, write_buf) = unsafe { tls_buffers::tls_buffers() };
-
-        debug!(
-            "TLS buffers allocated: read={} bytes, write={} bytes (main SRAM)",
-            read_buf.len(),
-            write_buf.len()
-        );
-
-        // Step 5: Configure TLS with server name for SNI
-        let config = TlsConfig::new().with_server_name(self.config.server_name);
-
-        if self.config.verify_server {
-            warn!("Certificate verification requested but not yet implemented");
-            warn!("Phase 1: proceeding without verification");
-        }
-
-        // Step 6: Create TLS connection with buffers (using AES-128-GCM-SHA256)
-        let mut tls_connection =
-            TlsConnection::<AsyncTcpSocket, Aes128GcmSha256>::new(socket, read_buf, write_buf);
-
-        // Step 7: Create crypto provider and TLS context, then perform handshake
-        info!("Initiating TLS 1.3 handshake with hardware RNG...");
-        let provider = SimpleCryptoProvider::new(rng);
-        let tls_context = TlsContext::new(&config, provider);
-
-        tls_connection.open(tls_context).await.map_err(|e| {
-            error!("TLS handshake failed: {:?}", Debug2Format(&e));
-            TlsError::HandshakeFailed
-        })?;
-
-        info!("TLS 1.3 handshake completed successfully!");
-
-        // Step 8: Close the connection
-        tls_connection.close().await.map_err(|(_socket, e)| {
-            warn!("TLS close returned error: {:?}", Debug2Format(&e));
-            TlsError::ConnectionClosed
-        })?;
+        let connector = SecureConnector::new(self.config);
+        let stream = connector
+            .connect(stack, rng, &mut rx_buffer, &mut tx_buffer)
+            .await?;
 
+        stream.close().await?;
         info!("TLS connection closed cleanly");
         Ok(())
     }
@@ -253,5 +387,104 @@ mod tests {
         assert_eq!(config.server_name, "broker.emqx.io");
         assert_eq!(config.server_port, 8883);
         assert!(!config.verify_server);
+        assert!(config.ca_certificates.is_empty());
+        assert!(config.pins.is_empty());
+        assert!(config.client_identity.is_none());
+        assert!(config.psk.is_none());
+        assert_eq!(config.auth_mode(), AuthMode::Unverified);
+        assert_eq!(config.cipher_suite, CipherSuite::Aes128GcmSha256);
+        assert_eq!(config.max_fragment_length, tls_buffers::FRAGMENT_LENGTH);
+        assert!(config.alpn_protocols.is_empty());
+        assert!(config.fallback_port.is_none());
+        assert_eq!(config.tcp.connect_timeout_ms, 10_000);
+        assert!(config.tcp.read_timeout_ms.is_none());
+        assert_eq!(config.handshake_timeout_ms, 15_000);
+    }
+
+    #[test]
+    fn test_fragment_length_bytes() {
+        assert_eq!(FragmentLength::Bytes512.bytes(), 1 << 9);
+        assert_eq!(FragmentLength::Bytes4096.bytes(), 1 << 12);
+    }
+
+    #[test]
+    fn test_auth_mode_precedence() {
+        let psk = PskCredentials {
+            identity: b"gateway-01",
+            key: &[0x42; 32],
+        };
+        let mut config = TlsClientConfig {
+            verify_server: true,
+            ca_certificates: &[CA_DER],
+            ..Default::default()
+        };
+        assert_eq!(config.auth_mode(), AuthMode::ServerCertificate);
+
+        config.client_identity = Some(ClientIdentity {
+            certificate: LEAF_DER,
+            private_key: LEAF_KEY_DER,
+        });
+        assert_eq!(config.auth_mode(), AuthMode::MutualCertificate);
+
+        config.psk = Some(psk);
+        assert_eq!(config.auth_mode(), AuthMode::PskDhe);
+    }
+
+    #[test]
+    fn test_psk_validation() {
+        let valid = PskCredentials {
+            identity: b"gateway-01",
+            key: &[0x42; MIN_PSK_LEN],
+        };
+        assert!(valid.validate().is_ok());
+
+        let short_key = PskCredentials {
+            key: &[0x42; MIN_PSK_LEN - 1],
+            ..valid
+        };
+        assert!(matches!(
+            short_key.validate(),
+            Err(TlsError::ClientIdentityError)
+        ));
+
+        let no_identity = PskCredentials {
+            identity: b"",
+            ..valid
+        };
+        assert!(no_identity.validate().is_err());
+    }
+
+    const CA_DER: &[u8] = include_bytes!("testdata/ca.der");
+    const LEAF_DER: &[u8] = include_bytes!("testdata/leaf.der");
+    const LEAF_KEY_DER: &[u8] = include_bytes!("testdata/leaf.key.der");
+
+    #[test]
+    fn test_client_identity_matching_pair() {
+        let identity = ClientIdentity {
+            certificate: LEAF_DER,
+            private_key: LEAF_KEY_DER,
+        };
+        assert!(identity.signing_key().is_ok());
+    }
+
+    #[test]
+    fn test_client_identity_mismatch() {
+        let mismatched = ClientIdentity {
+            certificate: CA_DER,
+            private_key: LEAF_KEY_DER,
+        };
+        assert!(matches!(
+            mismatched.signing_key(),
+            Err(TlsError::ClientIdentityError)
+        ));
+
+        let garbage = ClientIdentity {
+            certificate: LEAF_DER,
+            private_key: &[0x30, 0x00],
+        };
+        assert!(matches!(
+            garbage.signing_key(),
+            Err(TlsError::ClientIdentityError)
+        ));
     }
 }
@@ -0,0 +1,361 @@
+#![deny(unsafe_code)]
+#![deny(warnings)]
+//! TLS server certificate verification against a CA bundle or pins (SR-NET-002)
+//!
+//! `ServerVerifier` plugs into embedded-tls as the `TlsVerifier` of our
+//! crypto provider. During the handshake it:
+//!
+//! 1. Verifies the server's certificate chain up to one of the configured
+//!    trust anchors (`x509::verify_chain`): signatures, validity period
+//!    against the RTC, and the broker hostname in the SubjectAltName
+//! 2. Checks the leaf against the configured SHA-256 pins, if any
+//! 3. Verifies the server's `CertificateVerify` signature over the handshake
+//!    transcript with the leaf key, proving possession of the private key
+//!
+//! Any failure aborts the handshake. The reason is recorded in a
+//! `VerificationOutcome` so the connector can report a pin mismatch as
+//! `TlsError::PinMismatch` and everything else as `TlsError::CertificateError`.
+//!
+//! # Pinning
+//!
+//! Pins are a lighter alternative to chain validation: with pins and no CA
+//! bundle only step 2 and 3 run, so no CA certificates need to be stored and
+//! no synchronized clock is needed. A `Pin::PublicKey` (hash of the DER
+//! `SubjectPublicKeyInfo`) survives certificate renewal with the same key; a
+//! `Pin::Certificate` (hash of the whole DER certificate) does not. List the
+//! current and the next pin to rotate keys without a firmware gap:
+//!
+//! ```text
+//! openssl x509 -in broker.crt -pubkey -noout | openssl pkey -pubin -outform DER | sha256sum
+//! openssl x509 -in broker.crt -outform DER | sha256sum
+//! ```
+//!
+//! Validity checks need wall-clock time, so chain verification refuses to
+//! run until SNTP has set the RTC. With neither CA bundle nor pins
+//! verification is disabled and every server is accepted (development
+//! brokers only).
+//!
+//! # Example
+//!
+//! ```no_run
+//! static CA_BUNDLE: &[&[u8]] = &[include_bytes!("AmazonRootCA3.der")];
+//!
+//! let config = TlsClientConfig {
+//!     server_name: "xxxx-ats.iot.us-east-1.amazonaws.com",
+//!     ca_certificates: CA_BUNDLE,
+//!     verify_server: true,
+//!     ..Default::default()
+//! };
+//! ```
+
+use core::cell::Cell;
+
+use defmt::{debug, error, warn, Format};
+use embedded_tls::{
+    CertificateEntryRef, CertificateRef, HandshakeVerifyRef, SignatureScheme, TlsCipherSuite,
+    TlsVerifier,
+};
+use heapless::Vec;
+use sha2::{Digest, Sha256};
+
+use super::x509::{self, Certificate, X509Error};
+use crate::time;
+
+/// Largest transcript hash of a supported cipher suite (SHA-384)
+const MAX_HASH_LEN: usize = 48;
+
+/// Length of an uncompressed SEC1 P-256 public key
+const P256_KEY_LEN: usize = 65;
+
+/// Context string for the server's CertificateVerify (RFC 8446 §4.4.3)
+const SERVER_CONTEXT: &[u8] = b"TLS 1.3, server CertificateVerify";
+
+/// Size of the signed CertificateVerify content: 64 spaces, context, 0x00, hash
+const SIGNED_CONTENT_LEN: usize = 64 + SERVER_CONTEXT.len() + 1 + MAX_HASH_LEN;
+
+/// SHA-256 pin on the server's leaf certificate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pin {
+    /// Hash of the complete DER certificate
+    Certificate([u8; 32]),
+    /// Hash of the DER `SubjectPublicKeyInfo`
+    PublicKey([u8; 32]),
+}
+
+/// Why the server was rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Format)]
+pub enum VerifyError {
+    /// Chain validation needs the RTC set by SNTP first
+    TimeNotSynced,
+    /// Chain, hostname or validity check failed
+    Certificate(X509Error),
+    /// Leaf certificate matches none of the pins
+    PinMismatch,
+    /// CertificateVerify missing, unsupported or invalid
+    Signature,
+}
+
+/// Failure recorded by `ServerVerifier`, read by the connector afterwards
+pub type VerificationOutcome = Cell<Option<VerifyError>>;
+
+/// Verifies the server certificate chain, pins and handshake signature
+pub struct ServerVerifier<'a> {
+    host: &'static str,
+    trust_anchors: &'static [&'static [u8]],
+    pins: &'static [Pin],
+    transcript_hash: Vec<u8, MAX_HASH_LEN>,
+    leaf_key: Option<Vec<u8, P256_KEY_LEN>>,
+    outcome: &'a VerificationOutcome,
+}
+
+impl<'a> ServerVerifier<'a> {
+    /// Create a verifier for `host`
+    ///
+    /// # Arguments
+    ///
+    /// * `host` - Expected hostname (SNI name) for chain validation
+    /// * `trust_anchors` - Trusted CA certificates (DER); empty skips chain validation
+    /// * `pins` - Accepted leaf pins; empty skips pinning
+    /// * `outcome` - Receives the failure reason if the server is rejected
+    ///
+    /// With both `trust_anchors` and `pins` empty verification is disabled.
+    pub fn new(
+        host: &'static str,
+        trust_anchors: &'static [&'static [u8]],
+        pins: &'static [Pin],
+        outcome: &'a VerificationOutcome,
+    ) -> Self {
+        Self {
+            host,
+            trust_anchors,
+            pins,
+            transcript_hash: Vec::new(),
+            leaf_key: None,
+            outcome,
+        }
+    }
+
+    /// Whether certificates are actually checked
+    pub fn is_enabled(&self) -> bool {
+        !self.trust_anchors.is_empty() || !self.pins.is_empty()
+    }
+
+    /// Check the server chain and pins, and remember the leaf key
+    fn check_certificate(&mut self, certificate: &CertificateRef) -> Result<(), VerifyError> {
+        let mut chain: Vec<&[u8], { x509::MAX_CHAIN_DEPTH + 1 }> = Vec::new();
+        for entry in certificate.entries.iter() {
+            if let CertificateEntryRef::X509(der) = entry {
+                if chain.push(der).is_err() {
+                    warn!("Server sent more than {} certificates", chain.len());
+                    break;
+                }
+            }
+        }
+        let leaf_der = chain
+            .first()
+            .copied()
+            .ok_or(VerifyError::Certificate(X509Error::Malformed))?;
+
+        let leaf = if self.trust_anchors.is_empty() {
+            // Pinning only: the pin identifies the server, so neither the
+            // issuer nor the hostname and validity period matter
+            Certificate::from_der(leaf_der).map_err(VerifyError::Certificate)?
+        } else {
+            if !time::is_time_synced() {
+                return Err(VerifyError::TimeNotSynced);
+            }
+            let now = time::get_timestamp().unix_secs;
+            x509::verify_chain(&chain, self.trust_anchors, self.host, now)
+                .map_err(VerifyError::Certificate)?
+        };
+
+        if !self.pins.is_empty() && !matches_pin(self.pins, leaf_der, &leaf) {
+            return Err(VerifyError::PinMismatch);
+        }
+
+        self.leaf_key = Vec::from_slice(leaf.public_key).ok();
+        Ok(())
+    }
+
+    /// Record and log a rejection
+    fn reject(&self, reason: VerifyError) {
+        error!("Server certificate rejected: {:?}", reason);
+        self.outcome.set(Some(reason));
+    }
+}
+
+impl<CipherSuite> TlsVerifier<CipherSuite> for ServerVerifier<'_>
+where
+    CipherSuite: TlsCipherSuite,
+{
+    fn set_hostname_verification(&mut self, _hostname: &str) -> Result<(), embedded_tls::TlsError> {
+        // The hostname comes from the same config as the SNI name, so the one
+        // given at construction is already the right one.
+        Ok(())
+    }
+
+    fn verify_certificate(
+        &mut self,
+        transcript: &CipherSuite::Hash,
+        certificate: CertificateRef,
+    ) -> Result<(), embedded_tls::TlsError> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+
+        self.check_certificate(&certificate).map_err(|reason| {
+            self.reject(reason);
+            embedded_tls::TlsError::InvalidCertificate
+        })?;
+
+        // CertificateVerify signs the transcript up to and including the
+        // Certificate message, which is the state handed to us here.
+        self.transcript_hash = Vec::from_slice(&transcript.clone().finalize())
+            .map_err(|_| embedded_tls::TlsError::InvalidCertificate)?;
+
+        debug!("Server certificate verified for {}", self.host);
+        Ok(())
+    }
+
+    fn verify_signature(
+        &mut self,
+        verify: HandshakeVerifyRef,
+    ) -> Result<(), embedded_tls::TlsError> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+
+        let Some(leaf_key) = self.leaf_key.as_ref() else {
+            error!("CertificateVerify received before a verified certificate");
+            self.reject(VerifyError::Signature);
+            return Err(embedded_tls::TlsError::InvalidSignature);
+        };
+
+        if verify.signature_scheme != SignatureScheme::EcdsaSecp256r1Sha256 {
+            error!("Unsupported CertificateVerify signature scheme");
+            self.reject(VerifyError::Signature);
+            return Err(embedded_tls::TlsError::InvalidSignature);
+        }
+
+        let content = signed_content(&self.transcript_hash);
+        x509::verify_signature(leaf_key, &content, verify.signature).map_err(|_| {
+            self.reject(VerifyError::Signature);
+            embedded_tls::TlsError::InvalidSignature
+        })
+    }
+}
+
+/// Whether the leaf certificate matches any of `pins`
+///
+/// # Arguments
+///
+/// * `pins` - Accepted pins
+/// * `der` - The leaf certificate exactly as sent by the server
+/// * `certificate` - `der` parsed
+pub fn matches_pin(pins: &[Pin], der: &[u8], certificate: &Certificate) -> bool {
+    let certificate_hash: [u8; 32] = Sha256::digest(der).into();
+    let spki_hash: [u8; 32] = Sha256::digest(certificate.spki).into();
+
+    pins.iter().any(|pin| match pin {
+        Pin::Certificate(hash) => *hash == certificate_hash,
+        Pin::PublicKey(hash) => *hash == spki_hash,
+    })
+}
+
+/// Build the content covered by the server's CertificateVerify signature
+///
+/// RFC 8446 §4.4.3: 64 bytes of 0x20, the context string, a zero byte and
+/// the transcript hash.
+fn signed_content(transcript_hash: &[u8]) -> Vec<u8, SIGNED_CONTENT_LEN> {
+    let mut content = Vec::new();
+    // Cannot fail: the transcript hash is at most MAX_HASH_LEN bytes
+    let _ = content.resize(64, 0x20);
+    let _ = content.extend_from_slice(SERVER_CONTEXT);
+    let _ = content.push(0);
+    let _ = content.extend_from_slice(transcript_hash);
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEAF_DER: &[u8] = include_bytes!("testdata/leaf.der");
+
+    /// `openssl x509 -inform DER -in leaf.der -outform DER | sha256sum`
+    const LEAF_CERT_SHA256: [u8; 32] = [
+        0x37, 0x8e, 0xf3, 0xd8, 0x14, 0x7d, 0x8e, 0x06, 0xbe, 0x87, 0x86, 0x0e, 0x41, 0x74, 0xa7,
+        0x3c, 0x8f, 0xba, 0xd8, 0xf4, 0x84, 0x47, 0x01, 0x3b, 0xde, 0xa4, 0x60, 0xdc, 0xac, 0xda,
+        0xa5, 0xc9,
+    ];
+
+    /// SHA-256 of the leaf's DER SubjectPublicKeyInfo
+    const LEAF_SPKI_SHA256: [u8; 32] = [
+        0x14, 0x5f, 0xda, 0xf2, 0x35, 0xc0, 0x34, 0xd0, 0xb6, 0x63, 0x6a, 0x28, 0xb0, 0x83, 0x29,
+        0x74, 0x68, 0xa4, 0x12, 0xca, 0x9a, 0x94, 0x12, 0x64, 0x68, 0x4e, 0x85, 0xf8, 0x36, 0x78,
+        0xb1, 0xf9,
+    ];
+
+    #[test]
+    fn test_signed_content_layout() {
+        let hash = [0xAB; 32];
+        let content = signed_content(&hash);
+
+        assert_eq!(content.len(), 64 + 33 + 1 + 32);
+        assert!(content[..64].iter().all(|&b| b == 0x20));
+        assert_eq!(&content[64..97], SERVER_CONTEXT);
+        assert_eq!(content[97], 0);
+        assert_eq!(&content[98..], &hash);
+    }
+
+    #[test]
+    fn test_disabled_without_anchors_or_pins() {
+        let outcome = VerificationOutcome::new(None);
+        assert!(!ServerVerifier::new("broker.local", &[], &[], &outcome).is_enabled());
+        assert!(
+            ServerVerifier::new("broker.local", &[b"der" as &[u8]], &[], &outcome).is_enabled()
+        );
+        assert!(
+            ServerVerifier::new("broker.local", &[], &[Pin::PublicKey([0; 32])], &outcome)
+                .is_enabled()
+        );
+    }
+
+    #[test]
+    fn test_pins_match() {
+        let leaf = Certificate::from_der(LEAF_DER).unwrap();
+
+        assert!(matches_pin(
+            &[Pin::Certificate(LEAF_CERT_SHA256)],
+            LEAF_DER,
+            &leaf
+        ));
+        assert!(matches_pin(
+            &[Pin::PublicKey(LEAF_SPKI_SHA256)],
+            LEAF_DER,
+            &leaf
+        ));
+
+        // The old pin during a key rotation does not get in the way
+        let rotating = [Pin::PublicKey([0; 32]), Pin::PublicKey(LEAF_SPKI_SHA256)];
+        assert!(matches_pin(&rotating, LEAF_DER, &leaf));
+    }
+
+    #[test]
+    fn test_pins_mismatch() {
+        let leaf = Certificate::from_der(LEAF_DER).unwrap();
+
+        // A certificate hash is not accepted as a key pin and vice versa
+        assert!(!matches_pin(
+            &[Pin::PublicKey(LEAF_CERT_SHA256)],
+            LEAF_DER,
+            &leaf
+        ));
+        assert!(!matches_pin(
+            &[Pin::Certificate(LEAF_SPKI_SHA256)],
+            LEAF_DER,
+            &leaf
+        ));
+        assert!(!matches_pin(&[], LEAF_DER, &leaf));
+    }
+}
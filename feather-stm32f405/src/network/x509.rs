@@ -0,0 +1,614 @@
+#![deny(unsafe_code)]
+#![deny(warnings)]
+//! Minimal X.509 certificate parsing for TLS server verification
+//!
+//! Parses just enough of a DER-encoded X.509 v3 certificate to verify a
+//! server chain on the device: the raw TBS bytes and signature, issuer and
+//! subject names (compared byte-for-byte), the validity period, the subject
+//! public key, and the Subject Alternative Name and Basic Constraints
+//! extensions. Everything borrows from the input buffer; nothing is copied.
+//!
+//! Only ECDSA P-256 keys and `ecdsa-with-SHA256` signatures are recognised,
+//! which covers AWS IoT Core ECC certificates and our Mosquitto test CA.
+//! `verify_chain` ties parsing and signature checks together; it has no TLS
+//! dependencies, so it can be tested on the host.
+
+use core::net::Ipv4Addr;
+
+use p256::ecdsa::signature::Verifier;
+use p256::ecdsa::{Signature, VerifyingKey};
+
+use crate::time::civil_to_unix;
+
+/// DER tag: BOOLEAN
+const TAG_BOOLEAN: u8 = 0x01;
+/// DER tag: INTEGER
+const TAG_INTEGER: u8 = 0x02;
+/// DER tag: BIT STRING
+const TAG_BIT_STRING: u8 = 0x03;
+/// DER tag: OCTET STRING
+const TAG_OCTET_STRING: u8 = 0x04;
+/// DER tag: OBJECT IDENTIFIER
+const TAG_OID: u8 = 0x06;
+/// DER tag: UTCTime
+const TAG_UTC_TIME: u8 = 0x17;
+/// DER tag: GeneralizedTime
+const TAG_GENERALIZED_TIME: u8 = 0x18;
+/// DER tag: SEQUENCE
+const TAG_SEQUENCE: u8 = 0x30;
+/// Context tag [0] EXPLICIT (version)
+const TAG_VERSION: u8 = 0xA0;
+/// Context tag [3] EXPLICIT (extensions)
+const TAG_EXTENSIONS: u8 = 0xA3;
+/// GeneralName tag dNSName [2] IMPLICIT IA5String
+const TAG_SAN_DNS: u8 = 0x82;
+/// GeneralName tag iPAddress [7] IMPLICIT OCTET STRING
+const TAG_SAN_IP: u8 = 0x87;
+
+/// 1.2.840.10045.2.1 (id-ecPublicKey)
+const OID_EC_PUBLIC_KEY: &[u8] = &[0x2A, 0x86, 0x48, 0xCE, 0x3D, 0x02, 0x01];
+/// 1.2.840.10045.3.1.7 (prime256v1 / secp256r1)
+const OID_P256: &[u8] = &[0x2A, 0x86, 0x48, 0xCE, 0x3D, 0x03, 0x01, 0x07];
+/// 1.2.840.10045.4.3.2 (ecdsa-with-SHA256)
+const OID_ECDSA_SHA256: &[u8] = &[0x2A, 0x86, 0x48, 0xCE, 0x3D, 0x04, 0x03, 0x02];
+/// 2.5.29.17 (subjectAltName)
+const OID_SUBJECT_ALT_NAME: &[u8] = &[0x55, 0x1D, 0x11];
+/// 2.5.29.19 (basicConstraints)
+const OID_BASIC_CONSTRAINTS: &[u8] = &[0x55, 0x1D, 0x13];
+
+/// Maximum number of certificates between the leaf and a trust anchor
+pub const MAX_CHAIN_DEPTH: usize = 4;
+
+/// Certificate parsing and validation errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum X509Error {
+    /// Malformed or truncated DER
+    Malformed,
+    /// Key or signature algorithm other than ECDSA P-256 / SHA-256
+    UnsupportedAlgorithm,
+    /// Current time is before `notBefore`
+    NotYetValid,
+    /// Current time is after `notAfter`
+    Expired,
+    /// Hostname or IP address not listed in the Subject Alternative Name
+    NameMismatch,
+    /// Signature does not verify with the issuer's key
+    BadSignature,
+    /// No trust anchor or intermediate issued the certificate
+    UnknownIssuer,
+    /// Intermediate certificate is not marked as a CA
+    NotCa,
+}
+
+/// Parsed view of a DER certificate
+#[derive(Debug, Clone, Copy)]
+pub struct Certificate<'a> {
+    /// Complete DER encoding of `tbsCertificate` (the signed bytes)
+    pub tbs: &'a [u8],
+    /// Raw DER of the issuer `Name`
+    pub issuer: &'a [u8],
+    /// Raw DER of the subject `Name`
+    pub subject: &'a [u8],
+    /// `notBefore` as Unix seconds
+    pub not_before: u64,
+    /// `notAfter` as Unix seconds
+    pub not_after: u64,
+    /// Raw DER of the `SubjectPublicKeyInfo` (the input to SPKI pins)
+    pub spki: &'a [u8],
+    /// Uncompressed SEC1 P-256 public key (0x04 || X || Y)
+    pub public_key: &'a [u8],
+    /// DER ECDSA signature over `tbs`
+    pub signature: &'a [u8],
+    /// Contents of the SubjectAltName `GeneralNames` sequence, if present
+    pub subject_alt_names: Option<&'a [u8]>,
+    /// Basic Constraints `cA` flag
+    pub is_ca: bool,
+}
+
+impl<'a> Certificate<'a> {
+    /// Parse a DER-encoded X.509 v3 certificate
+    ///
+    /// # Errors
+    ///
+    /// Returns `X509Error::Malformed` for invalid DER and
+    /// `X509Error::UnsupportedAlgorithm` for non-P-256/SHA-256 certificates.
+    pub fn from_der(der: &'a [u8]) -> Result<Self, X509Error> {
+        let (cert, rest) = expect(der, TAG_SEQUENCE)?;
+        if !rest.is_empty() {
+            return Err(X509Error::Malformed);
+        }
+
+        // Certificate ::= SEQUENCE { tbsCertificate, signatureAlgorithm, signatureValue }
+        let tbs_len = cert.len() - read_tlv(cert)?.2.len();
+        let tbs = &cert[..tbs_len];
+        let (tbs_body, rest) = expect(cert, TAG_SEQUENCE)?;
+        let (signature_algorithm, rest) = expect(rest, TAG_SEQUENCE)?;
+        let (signature, _) = bit_string(rest)?;
+
+        let (algorithm, _) = expect(signature_algorithm, TAG_OID)?;
+        if algorithm != OID_ECDSA_SHA256 {
+            return Err(X509Error::UnsupportedAlgorithm);
+        }
+
+        // TBSCertificate ::= SEQUENCE { [0] version, serialNumber, signature,
+        //   issuer, validity, subject, subjectPublicKeyInfo, ..., [3] extensions }
+        let mut body = tbs_body;
+        if body.first() == Some(&TAG_VERSION) {
+            body = read_tlv(body)?.2;
+        }
+        let (_serial, body) = expect(body, TAG_INTEGER)?;
+        let (_signature, body) = expect(body, TAG_SEQUENCE)?;
+        let (issuer, body) = raw_tlv(body, TAG_SEQUENCE)?;
+        let (validity, body) = expect(body, TAG_SEQUENCE)?;
+        let (subject, body) = raw_tlv(body, TAG_SEQUENCE)?;
+        let (spki, mut body) = raw_tlv(body, TAG_SEQUENCE)?;
+
+        let (not_before, validity) = parse_time(validity)?;
+        let (not_after, _) = parse_time(validity)?;
+        let public_key = parse_p256_key(expect(spki, TAG_SEQUENCE)?.0)?;
+
+        let mut subject_alt_names = None;
+        let mut is_ca = false;
+        while !body.is_empty() {
+            let (tag, value, rest) = read_tlv(body)?;
+            if tag == TAG_EXTENSIONS {
+                let (extensions, _) = expect(value, TAG_SEQUENCE)?;
+                parse_extensions(extensions, &mut subject_alt_names, &mut is_ca)?;
+            }
+            body = rest;
+        }
+
+        Ok(Self {
+            tbs,
+            issuer,
+            subject,
+            not_before,
+            not_after,
+            spki,
+            public_key,
+            signature,
+            subject_alt_names,
+            is_ca,
+        })
+    }
+
+    /// Check the validity period against the current Unix time
+    ///
+    /// # Errors
+    ///
+    /// Returns `X509Error::NotYetValid` or `X509Error::Expired`.
+    pub fn check_validity(&self, now_unix: u64) -> Result<(), X509Error> {
+        if now_unix < self.not_before {
+            Err(X509Error::NotYetValid)
+        } else if now_unix > self.not_after {
+            Err(X509Error::Expired)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Check that `host` (DNS name or IPv4 literal) is covered by the SAN
+    ///
+    /// DNS names match case-insensitively, with a single leading `*` label
+    /// matching exactly one label (RFC 6125 §6.4.3). The subject common name
+    /// is not consulted.
+    ///
+    /// # Errors
+    ///
+    /// Returns `X509Error::NameMismatch` if no entry matches.
+    pub fn check_hostname(&self, host: &str) -> Result<(), X509Error> {
+        let mut names = self.subject_alt_names.ok_or(X509Error::NameMismatch)?;
+        let ip = host.parse::<Ipv4Addr>().ok();
+
+        while !names.is_empty() {
+            let (tag, value, rest) = read_tlv(names)?;
+            let matched = match (tag, ip) {
+                (TAG_SAN_IP, Some(ip)) => value == ip.octets(),
+                (TAG_SAN_DNS, None) => core::str::from_utf8(value)
+                    .map(|pattern| dns_name_matches(pattern, host))
+                    .unwrap_or(false),
+                _ => false,
+            };
+            if matched {
+                return Ok(());
+            }
+            names = rest;
+        }
+        Err(X509Error::NameMismatch)
+    }
+
+    /// Whether `issuer` names this certificate's issuer
+    pub fn is_issued_by(&self, issuer: &Certificate<'_>) -> bool {
+        self.issuer == issuer.subject
+    }
+}
+
+/// Verify a server chain against a set of trusted CA certificates
+///
+/// `chain` is the certificate list sent by the server, leaf first. The leaf
+/// must match `host` and every certificate up to a trust anchor must be
+/// within its validity period at `now_unix` and carry a valid signature from
+/// its issuer. Intermediates must have the Basic Constraints CA flag.
+///
+/// # Returns
+///
+/// The parsed leaf certificate, whose key verifies the handshake signature.
+///
+/// # Errors
+///
+/// Returns the first `X509Error` encountered.
+pub fn verify_chain<'a>(
+    chain: &[&'a [u8]],
+    trust_anchors: &[&[u8]],
+    host: &str,
+    now_unix: u64,
+) -> Result<Certificate<'a>, X509Error> {
+    let leaf_der = chain.first().ok_or(X509Error::Malformed)?;
+    let leaf = Certificate::from_der(leaf_der)?;
+    leaf.check_validity(now_unix)?;
+    leaf.check_hostname(host)?;
+
+    let mut current = leaf;
+    for _ in 0..MAX_CHAIN_DEPTH {
+        for anchor_der in trust_anchors {
+            // Anchors the parser cannot handle (e.g. RSA roots) never match
+            let Ok(anchor) = Certificate::from_der(anchor_der) else {
+                continue;
+            };
+            if current.is_issued_by(&anchor)
+                && verify_signature(anchor.public_key, current.tbs, current.signature).is_ok()
+            {
+                anchor.check_validity(now_unix)?;
+                return Ok(leaf);
+            }
+        }
+
+        let issuer = chain[1..]
+            .iter()
+            .filter_map(|der| Certificate::from_der(der).ok())
+            .find(|candidate| current.is_issued_by(candidate))
+            .ok_or(X509Error::UnknownIssuer)?;
+
+        if !issuer.is_ca {
+            return Err(X509Error::NotCa);
+        }
+        issuer.check_validity(now_unix)?;
+        verify_signature(issuer.public_key, current.tbs, current.signature)?;
+        current = issuer;
+    }
+    Err(X509Error::UnknownIssuer)
+}
+
+/// Verify an ECDSA P-256 / SHA-256 signature
+///
+/// # Arguments
+///
+/// * `public_key` - SEC1-encoded P-256 public key
+/// * `message` - Signed bytes (hashed with SHA-256 here)
+/// * `signature` - DER-encoded `ECDSA-Sig-Value`
+pub fn verify_signature(
+    public_key: &[u8],
+    message: &[u8],
+    signature: &[u8],
+) -> Result<(), X509Error> {
+    let key =
+        VerifyingKey::from_sec1_bytes(public_key).map_err(|_| X509Error::UnsupportedAlgorithm)?;
+    let signature = Signature::from_der(signature).map_err(|_| X509Error::BadSignature)?;
+    key.verify(message, &signature)
+        .map_err(|_| X509Error::BadSignature)
+}
+
+/// Match a SAN dNSName pattern against a hostname
+pub fn dns_name_matches(pattern: &str, host: &str) -> bool {
+    let host = host.strip_suffix('.').unwrap_or(host);
+    match pattern.strip_prefix("*.") {
+        Some(suffix) => match host.split_once('.') {
+            Some((label, rest)) => !label.is_empty() && rest.eq_ignore_ascii_case(suffix),
+            None => false,
+        },
+        None => pattern.eq_ignore_ascii_case(host),
+    }
+}
+
+/// Read one TLV: returns `(tag, value, rest)`
+fn read_tlv(input: &[u8]) -> Result<(u8, &[u8], &[u8]), X509Error> {
+    let (&tag, input) = input.split_first().ok_or(X509Error::Malformed)?;
+    let (&first, mut input) = input.split_first().ok_or(X509Error::Malformed)?;
+
+    let len = if first < 0x80 {
+        first as usize
+    } else {
+        let count = (first & 0x7F) as usize;
+        if count == 0 || count > 3 || input.len() < count {
+            return Err(X509Error::Malformed);
+        }
+        let len = input[..count]
+            .iter()
+            .fold(0usize, |acc, &b| (acc << 8) | b as usize);
+        input = &input[count..];
+        len
+    };
+
+    if input.len() < len {
+        return Err(X509Error::Malformed);
+    }
+    Ok((tag, &input[..len], &input[len..]))
+}
+
+/// Read a TLV with the expected tag: returns `(value, rest)`
+fn expect(input: &[u8], tag: u8) -> Result<(&[u8], &[u8]), X509Error> {
+    match read_tlv(input)? {
+        (t, value, rest) if t == tag => Ok((value, rest)),
+        _ => Err(X509Error::Malformed),
+    }
+}
+
+/// Like `expect`, but returns the complete encoding instead of the value
+fn raw_tlv(input: &[u8], tag: u8) -> Result<(&[u8], &[u8]), X509Error> {
+    let (_, rest) = expect(input, tag)?;
+    Ok((&input[..input.len() - rest.len()], rest))
+}
+
+/// Read a BIT STRING without unused bits: returns `(bits, rest)`
+fn bit_string(input: &[u8]) -> Result<(&[u8], &[u8]), X509Error> {
+    let (value, rest) = expect(input, TAG_BIT_STRING)?;
+    match value.split_first() {
+        Some((0, bits)) => Ok((bits, rest)),
+        _ => Err(X509Error::Malformed),
+    }
+}
+
+/// Parse `SubjectPublicKeyInfo` contents, accepting only P-256 keys
+fn parse_p256_key(spki: &[u8]) -> Result<&[u8], X509Error> {
+    let (algorithm, rest) = expect(spki, TAG_SEQUENCE)?;
+    let (key_type, params) = expect(algorithm, TAG_OID)?;
+    let (curve, _) = expect(params, TAG_OID)?;
+    if key_type != OID_EC_PUBLIC_KEY || curve != OID_P256 {
+        return Err(X509Error::UnsupportedAlgorithm);
+    }
+
+    let (key, _) = bit_string(rest)?;
+    if key.len() != 65 || key[0] != 0x04 {
+        return Err(X509Error::UnsupportedAlgorithm);
+    }
+    Ok(key)
+}
+
+/// Walk the extension list, picking out SAN and Basic Constraints
+fn parse_extensions<'a>(
+    mut extensions: &'a [u8],
+    subject_alt_names: &mut Option<&'a [u8]>,
+    is_ca: &mut bool,
+) -> Result<(), X509Error> {
+    while !extensions.is_empty() {
+        // Extension ::= SEQUENCE { extnID, critical BOOLEAN DEFAULT FALSE, extnValue }
+        let (extension, rest) = expect(extensions, TAG_SEQUENCE)?;
+        let (oid, mut fields) = expect(extension, TAG_OID)?;
+        if fields.first() == Some(&TAG_BOOLEAN) {
+            fields = read_tlv(fields)?.2;
+        }
+        let (value, _) = expect(fields, TAG_OCTET_STRING)?;
+
+        if oid == OID_SUBJECT_ALT_NAME {
+            *subject_alt_names = Some(expect(value, TAG_SEQUENCE)?.0);
+        } else if oid == OID_BASIC_CONSTRAINTS {
+            let (constraints, _) = expect(value, TAG_SEQUENCE)?;
+            *is_ca = matches!(read_tlv(constraints), Ok((TAG_BOOLEAN, [0xFF], _)));
+        }
+        extensions = rest;
+    }
+    Ok(())
+}
+
+/// Parse a UTCTime or GeneralizedTime: returns `(unix_secs, rest)`
+fn parse_time(input: &[u8]) -> Result<(u64, &[u8]), X509Error> {
+    let (tag, value, rest) = read_tlv(input)?;
+    let (year, digits) = match (tag, value.len()) {
+        // YYMMDDHHMMSSZ; RFC 5280 §4.1.2.5.1: YY < 50 means 20YY
+        (TAG_UTC_TIME, 13) => {
+            let yy = two_digits(value, 0)? as u16;
+            (if yy < 50 { 2000 + yy } else { 1900 + yy }, &value[2..])
+        }
+        // YYYYMMDDHHMMSSZ
+        (TAG_GENERALIZED_TIME, 15) => {
+            let year = two_digits(value, 0)? as u16 * 100 + two_digits(value, 2)? as u16;
+            (year, &value[4..])
+        }
+        _ => return Err(X509Error::Malformed),
+    };
+
+    if digits.last() != Some(&b'Z') || year < 1970 {
+        return Err(X509Error::Malformed);
+    }
+
+    let month = two_digits(digits, 0)?;
+    let day = two_digits(digits, 2)?;
+    let hour = two_digits(digits, 4)?;
+    let minute = two_digits(digits, 6)?;
+    // 60 is allowed for a leap second
+    let second = two_digits(digits, 8)?;
+    if !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hour >= 24
+        || minute >= 60
+        || second >= 61
+    {
+        return Err(X509Error::Malformed);
+    }
+
+    let unix = civil_to_unix(year, month, day, hour, minute, second);
+    Ok((unix, rest))
+}
+
+fn two_digits(bytes: &[u8], at: usize) -> Result<u8, X509Error> {
+    match bytes.get(at..at + 2) {
+        Some([a @ b'0'..=b'9', b @ b'0'..=b'9']) => Ok((a - b'0') * 10 + (b - b'0')),
+        _ => Err(X509Error::Malformed),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CA_DER: &[u8] = include_bytes!("testdata/ca.der");
+    const LEAF_DER: &[u8] = include_bytes!("testdata/leaf.der");
+
+    /// 2024-01-01T00:00:00Z
+    const JAN_2024: u64 = 1_704_067_200;
+    /// 2034-01-01T00:00:00Z
+    const JAN_2034: u64 = 2_019_686_400;
+
+    #[test]
+    fn test_parse_ca() {
+        let ca = Certificate::from_der(CA_DER).unwrap();
+        assert!(ca.is_ca);
+        assert_eq!(ca.issuer, ca.subject);
+        assert_eq!(ca.public_key.len(), 65);
+        assert_eq!(ca.not_before, JAN_2024);
+        assert_eq!(ca.not_after, JAN_2034);
+    }
+
+    #[test]
+    fn test_parse_leaf() {
+        let ca = Certificate::from_der(CA_DER).unwrap();
+        let leaf = Certificate::from_der(LEAF_DER).unwrap();
+        assert!(!leaf.is_ca);
+        assert!(leaf.is_issued_by(&ca));
+        assert!(!ca.is_issued_by(&leaf));
+        assert!(leaf.subject_alt_names.is_some());
+        assert_eq!(leaf.tbs[0], TAG_SEQUENCE);
+    }
+
+    #[test]
+    fn test_validity() {
+        let leaf = Certificate::from_der(LEAF_DER).unwrap();
+        assert_eq!(leaf.check_validity(JAN_2024 + 1), Ok(()));
+        assert_eq!(
+            leaf.check_validity(JAN_2024 - 1),
+            Err(X509Error::NotYetValid)
+        );
+        assert_eq!(leaf.check_validity(JAN_2034 + 1), Err(X509Error::Expired));
+    }
+
+    #[test]
+    fn test_hostname() {
+        let leaf = Certificate::from_der(LEAF_DER).unwrap();
+        assert_eq!(leaf.check_hostname("broker.local"), Ok(()));
+        assert_eq!(leaf.check_hostname("BROKER.local"), Ok(()));
+        assert_eq!(leaf.check_hostname("mqtt.iot.local"), Ok(()));
+        assert_eq!(leaf.check_hostname("192.168.1.1"), Ok(()));
+        assert_eq!(
+            leaf.check_hostname("a.b.iot.local"),
+            Err(X509Error::NameMismatch)
+        );
+        assert_eq!(
+            leaf.check_hostname("192.168.1.2"),
+            Err(X509Error::NameMismatch)
+        );
+        assert_eq!(
+            leaf.check_hostname("evil.example"),
+            Err(X509Error::NameMismatch)
+        );
+    }
+
+    #[test]
+    fn test_wildcard_rules() {
+        assert!(dns_name_matches("*.example.com", "a.example.com"));
+        assert!(!dns_name_matches("*.example.com", "example.com"));
+        assert!(!dns_name_matches("*.example.com", ".example.com"));
+        assert!(dns_name_matches("example.com", "example.com."));
+    }
+
+    #[test]
+    fn test_truncated_der_rejected() {
+        assert_eq!(
+            Certificate::from_der(&LEAF_DER[..LEAF_DER.len() - 1]).err(),
+            Some(X509Error::Malformed)
+        );
+        assert_eq!(Certificate::from_der(&[]).err(), Some(X509Error::Malformed));
+    }
+
+    #[test]
+    fn test_chain_to_trust_anchor() {
+        let leaf = verify_chain(&[LEAF_DER], &[CA_DER], "broker.local", JAN_2024 + 1).unwrap();
+        assert_eq!(leaf.tbs, Certificate::from_der(LEAF_DER).unwrap().tbs);
+
+        // An anchor the parser rejects does not hide the one that matches
+        let unsupported: &[u8] = &[0x30, 0x00];
+        assert!(verify_chain(
+            &[LEAF_DER],
+            &[unsupported, CA_DER],
+            "broker.local",
+            JAN_2024 + 1
+        )
+        .is_ok());
+
+        // A server sending the CA along is fine too
+        assert!(verify_chain(&[LEAF_DER, CA_DER], &[CA_DER], "broker.local", JAN_2024 + 1).is_ok());
+    }
+
+    #[test]
+    fn test_chain_failures() {
+        let now = JAN_2024 + 1;
+        assert_eq!(
+            verify_chain(&[LEAF_DER], &[CA_DER], "other.local", now).err(),
+            Some(X509Error::NameMismatch)
+        );
+        assert_eq!(
+            verify_chain(&[LEAF_DER], &[CA_DER], "broker.local", JAN_2034 + 1).err(),
+            Some(X509Error::Expired)
+        );
+        assert_eq!(
+            verify_chain(&[LEAF_DER], &[], "broker.local", now).err(),
+            Some(X509Error::UnknownIssuer)
+        );
+        assert_eq!(
+            verify_chain(&[], &[CA_DER], "broker.local", now).err(),
+            Some(X509Error::Malformed)
+        );
+    }
+
+    #[test]
+    fn test_tampered_signature_rejected() {
+        let leaf = Certificate::from_der(LEAF_DER).unwrap();
+        let ca = Certificate::from_der(CA_DER).unwrap();
+        assert_eq!(
+            verify_signature(ca.public_key, leaf.tbs, leaf.signature),
+            Ok(())
+        );
+
+        let mut tampered = [0u8; 512];
+        let tbs = &mut tampered[..leaf.tbs.len()];
+        tbs.copy_from_slice(leaf.tbs);
+        tbs[20] ^= 0x01;
+        assert_eq!(
+            verify_signature(ca.public_key, tbs, leaf.signature),
+            Err(X509Error::BadSignature)
+        );
+    }
+
+    #[test]
+    fn test_time_parsing() {
+        let utc = b"\x17\x0d240101000000Z";
+        assert_eq!(parse_time(utc).unwrap().0, JAN_2024);
+        let generalized = b"\x18\x0f20340101000000Z";
+        assert_eq!(parse_time(generalized).unwrap().0, JAN_2034);
+        assert!(parse_time(b"\x17\x0d2401010000000").is_err());
+    }
+
+    #[test]
+    fn test_time_fields_out_of_range_rejected() {
+        for time in [
+            b"\x17\x0d240100000000Z", // day 00
+            b"\x17\x0d240001000000Z", // month 00
+            b"\x17\x0d241301000000Z", // month 13
+            b"\x17\x0d240132000000Z", // day 32
+            b"\x17\x0d240101240000Z", // hour 24
+            b"\x17\x0d240101006000Z", // minute 60
+            b"\x17\x0d240101000061Z", // second 61
+        ] {
+            assert_eq!(parse_time(time), Err(X509Error::Malformed));
+        }
+    }
+}
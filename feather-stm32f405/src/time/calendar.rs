@@ -75,16 +75,38 @@ pub fn unix_to_datetime(unix_secs: u64) -> DateTime {
 /// - UTC only (no timezone support)
 #[allow(dead_code)]
 pub fn datetime_to_unix(dt: DateTime) -> u64 {
+    civil_to_unix(
+        dt.year(),
+        dt.month(),
+        dt.day(),
+        dt.hour(),
+        dt.minute(),
+        dt.second(),
+    )
+}
+
+/// Convert a UTC civil date and time to a Unix timestamp
+///
+/// Used for RTC readings and for X.509 validity times. Dates before 1970
+/// are not supported.
+pub(crate) fn civil_to_unix(
+    year: u16,
+    month: u8,
+    day: u8,
+    hour: u8,
+    minute: u8,
+    second: u8,
+) -> u64 {
     const SECONDS_PER_DAY: u64 = 86400;
 
     // Convert civil date to days since Unix epoch using O(1) algorithm
-    let days_since_epoch = days_from_civil(dt.year(), dt.month(), dt.day());
+    let days_since_epoch = days_from_civil(year, month, day);
 
     // Convert to seconds and add time of day
     (days_since_epoch as u64) * SECONDS_PER_DAY
-        + (dt.hour() as u64) * 3600
-        + (dt.minute() as u64) * 60
-        + (dt.second() as u64)
+        + (hour as u64) * 3600
+        + (minute as u64) * 60
+        + (second as u64)
 }
 
 /// Convert days since Unix epoch to civil date (year, month, day)
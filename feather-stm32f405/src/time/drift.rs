@@ -0,0 +1,165 @@
+//! Clock frequency error estimation from successive time syncs
+//!
+//! Every sync steps a clock by the offset it has accumulated since the
+//! previous one. Summed over a long enough baseline, those steps divided by
+//! the elapsed time give the clock's residual frequency error, which is
+//! folded into the correction applied between syncs (a simple FLL, RFC 5905
+//! §11.3). The same estimator disciplines the TIM2-based wall clock and the
+//! LSE-driven RTC, so both keep time during network outages.
+//!
+//! A baseline of at least an hour keeps the estimate well below 1 ppm even
+//! with a few milliseconds of network jitter per sync.
+#![deny(unsafe_code)]
+#![deny(warnings)]
+
+/// Shortest baseline used for a frequency update (1 hour)
+pub const MIN_BASELINE_US: u64 = 3_600_000_000;
+
+/// Largest plausible frequency error (±500 ppm, RFC 5905 MAXFREQ)
+///
+/// Apparent errors beyond this are clock steps (the very first sync, a
+/// wrapped counter, an outlier) and restart the baseline instead.
+pub const MAX_FREQUENCY_PPB: i64 = 500_000;
+
+/// Frequency error estimator for one clock
+#[derive(Debug, Clone, Copy)]
+pub struct DriftEstimator {
+    /// Correction to apply, in parts per billion (positive = run faster)
+    frequency_ppb: i32,
+    /// Monotonic time at the start of the current baseline (µs)
+    baseline_start_us: Option<u64>,
+    /// Sum of the offsets stepped out since the baseline started (µs)
+    accumulated_us: i64,
+}
+
+impl DriftEstimator {
+    /// Create an estimator with no correction
+    pub const fn new() -> Self {
+        Self {
+            frequency_ppb: 0,
+            baseline_start_us: None,
+            accumulated_us: 0,
+        }
+    }
+
+    /// Current frequency correction in parts per billion
+    ///
+    /// Positive values mean the clock runs slow and must be sped up.
+    pub fn frequency_ppb(&self) -> i32 {
+        self.frequency_ppb
+    }
+
+    /// Start a new baseline at `mono_us`, keeping the current correction
+    pub fn restart(&mut self, mono_us: u64) {
+        self.baseline_start_us = Some(mono_us);
+        self.accumulated_us = 0;
+    }
+
+    /// Record a sync that stepped the clock by `offset_us` at `mono_us`
+    ///
+    /// # Arguments
+    /// * `mono_us` - Monotonic time of the sync in microseconds
+    /// * `offset_us` - True time minus clock time before the step
+    ///
+    /// # Returns
+    /// The new frequency correction when the baseline was long enough for
+    /// an update, otherwise `None`.
+    pub fn update(&mut self, mono_us: u64, offset_us: i64) -> Option<i32> {
+        let Some(start) = self.baseline_start_us else {
+            self.restart(mono_us);
+            return None;
+        };
+
+        let elapsed = mono_us.saturating_sub(start);
+        self.accumulated_us = self.accumulated_us.saturating_add(offset_us);
+        let residual_ppb = (i128::from(self.accumulated_us) * 1_000_000_000)
+            .checked_div(i128::from(elapsed))
+            .unwrap_or(i128::MAX);
+
+        if residual_ppb.unsigned_abs() > MAX_FREQUENCY_PPB as u128 {
+            self.restart(mono_us);
+            return None;
+        }
+        if elapsed < MIN_BASELINE_US {
+            return None;
+        }
+
+        let frequency = (i64::from(self.frequency_ppb) + residual_ppb as i64)
+            .clamp(-MAX_FREQUENCY_PPB, MAX_FREQUENCY_PPB);
+        self.frequency_ppb = frequency as i32;
+        self.restart(mono_us);
+        Some(self.frequency_ppb)
+    }
+}
+
+impl Default for DriftEstimator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE_US: u64 = 60_000_000;
+
+    /// Offset a clock with `error_ppb` and correction `correction_ppb`
+    /// accumulates over `interval_us`
+    fn offset_after(error_ppb: i64, correction_ppb: i32, interval_us: u64) -> i64 {
+        -(error_ppb + i64::from(correction_ppb)) * interval_us as i64 / 1_000_000_000
+    }
+
+    #[test]
+    fn test_first_sync_only_starts_baseline() {
+        let mut drift = DriftEstimator::new();
+        assert_eq!(drift.update(5_000_000, 1_700_000_000_000_000), None);
+        assert_eq!(drift.frequency_ppb(), 0);
+    }
+
+    #[test]
+    fn test_estimates_fast_clock() {
+        // Clock 30 ppm fast, synced every 15 minutes
+        let mut drift = DriftEstimator::new();
+        let mut now = 0;
+        drift.update(now, 0);
+
+        let mut estimate = None;
+        for _ in 0..4 {
+            now += 15 * MINUTE_US;
+            estimate = drift.update(
+                now,
+                offset_after(30_000, drift.frequency_ppb(), 15 * MINUTE_US),
+            );
+        }
+        assert_eq!(estimate, Some(-30_000));
+
+        // Once corrected, the clock no longer accumulates an offset
+        assert_eq!(
+            offset_after(30_000, drift.frequency_ppb(), 15 * MINUTE_US),
+            0
+        );
+    }
+
+    #[test]
+    fn test_waits_for_minimum_baseline() {
+        let mut drift = DriftEstimator::new();
+        drift.update(0, 0);
+        for step in 1..4 {
+            assert_eq!(drift.update(step * 15 * MINUTE_US, -27_000), None);
+        }
+        assert_eq!(drift.frequency_ppb(), 0);
+    }
+
+    #[test]
+    fn test_step_restarts_baseline() {
+        let mut drift = DriftEstimator::new();
+        drift.update(0, 0);
+        drift.update(30 * MINUTE_US, -54_000);
+
+        // Two seconds off after 15 minutes is not drift
+        assert_eq!(drift.update(45 * MINUTE_US, 2_000_000), None);
+        // The earlier offsets were discarded with the old baseline
+        assert_eq!(drift.update(105 * MINUTE_US, 36_000), Some(10_000));
+    }
+}
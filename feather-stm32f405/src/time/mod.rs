@@ -4,9 +4,13 @@
 //! per RFC 5905, fulfilling requirements SR-NET-006 and SR-NET-007.
 //!
 //! ## Architecture
-//! - SNTP client syncs with NTP servers every 15 minutes
+//! - SNTP client resyncs with NTP servers adaptively, every 15 minutes
+//!   once the clock is stable
 //! - Time is written to STM32 hardware internal RTC
 //! - Between syncs, timestamps are read from internal RTC hardware
+//! - Successive syncs estimate the frequency error of the wall clock (HSE)
+//!   and of the RTC (LSE, ±20-50 ppm); both are corrected, the RTC through
+//!   its smooth calibration register, to improve holdover during outages
 //! - Sync status stored atomically in CCM RAM
 //!
 //! ## Usage
@@ -16,9 +20,11 @@
 //!
 //! // SNTP client is in network::SntpClient
 //! let mut sntp = network::SntpClient::new();
-//! if let Ok(ts) = sntp.run(&stack).await {
-//!     info!("Time synced: {}.{:06}", ts.unix_secs, ts.micros);
+//! if let Ok(sync) = sntp.run(&stack).await {
+//!     info!("Time synced: {}.{:06}", sync.timestamp.unix_secs, sync.timestamp.micros);
 //! }
+//! // Keep resyncing next to the other clients (never returns)
+//! sntp.resync(&stack).await;
 //!
 //! // Get timestamp from internal RTC for sensor data
 //! let timestamp = time::get_timestamp();
@@ -28,11 +34,18 @@
 #![deny(warnings)]
 
 mod calendar;
+mod drift;
 mod rtc;
 
 // Re-export public API
+pub(crate) use calendar::civil_to_unix;
 #[allow(unused_imports)]
-pub use rtc::{get_timestamp, initialize_rtc, is_time_synced, write_rtc, RtcError, Timestamp};
+pub use drift::DriftEstimator;
+#[allow(unused_imports)]
+pub use rtc::{
+    calibrate_rtc, get_timestamp, initialize_rtc, is_time_synced, rtc_offset, write_rtc, RtcError,
+    Timestamp,
+};
 
 #[cfg(test)]
 use calendar::is_leap_year;
@@ -5,16 +5,28 @@
 use crate::ccmram::TIME_SYNCED;
 
 use core::cell::RefCell;
-use core::sync::atomic::Ordering;
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use critical_section::Mutex;
 use defmt::{info, Format};
-use embassy_stm32::rtc::Rtc;
+use embassy_stm32::rtc::{Rtc, RtcCalibrationCyclePeriod};
 
 use super::calendar::{datetime_to_unix, unix_to_datetime};
 
 /// Global internal RTC instance
 static RTC: Mutex<RefCell<Option<Rtc>>> = Mutex::new(RefCell::new(None));
 
+/// Sub-second part dropped by the last RTC write (microseconds)
+///
+/// The RTC only accepts whole seconds, so it lags true time by this much
+/// right after `write_rtc`; `rtc_offset` excludes it from the drift.
+static RTC_WRITE_LAG_MICROS: AtomicU32 = AtomicU32::new(0);
+
+/// Set once `write_rtc` has succeeded since boot
+///
+/// Until then the RTC holds whatever the backup domain kept, which says
+/// nothing about its drift.
+static RTC_WRITTEN: AtomicBool = AtomicBool::new(false);
+
 /// Timestamp with microsecond precision
 #[derive(Debug, Clone, Copy, Format)]
 pub struct Timestamp {
@@ -52,7 +64,11 @@ pub enum RtcError {
 }
 
 /// Initialize internal RTC
-pub fn initialize_rtc(rtc: Rtc) {
+///
+/// Clears any smooth calibration left in the backup domain, so the drift
+/// estimate built after boot matches what the hardware applies.
+pub fn initialize_rtc(mut rtc: Rtc) {
+    rtc.calibrate(0.0, RtcCalibrationCyclePeriod::Seconds32);
     critical_section::with(|cs| {
         RTC.borrow(cs).replace(Some(rtc));
     });
@@ -73,6 +89,8 @@ pub fn write_rtc(timestamp: Timestamp) -> Result<(), RtcError> {
         if let Some(rtc) = RTC.borrow(cs).borrow_mut().as_mut() {
             rtc.set_datetime(datetime)
                 .map_err(|_| RtcError::HardwareError)?;
+            RTC_WRITE_LAG_MICROS.store(timestamp.micros, Ordering::Release);
+            RTC_WRITTEN.store(true, Ordering::Release);
             TIME_SYNCED.store(true, Ordering::Release);
             Ok(())
         } else {
@@ -91,14 +109,57 @@ pub fn read_rtc() -> Result<Timestamp, RtcError> {
     critical_section::with(|cs| {
         if let Some(rtc) = RTC.borrow(cs).borrow_mut().as_mut() {
             let datetime = rtc.now().map_err(|_| RtcError::HardwareError)?;
+            let micros = datetime.usecond();
             let unix_secs = datetime_to_unix(datetime);
-            Ok(Timestamp::new(unix_secs, 0))
+            Ok(Timestamp::new(unix_secs, micros))
+        } else {
+            Err(RtcError::NotInitialized)
+        }
+    })
+}
+
+/// Error of the RTC against `now` accumulated since the last write
+///
+/// Compares the RTC (sub-second resolution from RTC_SSR, ~4 ms) with the
+/// true time `now`, less the lag from truncating the last write to whole
+/// seconds. Positive values mean the RTC runs slow.
+///
+/// # Errors
+/// Returns `RtcError::NotInitialized` before the first `write_rtc`.
+pub fn rtc_offset(now: Timestamp) -> Result<i64, RtcError> {
+    if !RTC_WRITTEN.load(Ordering::Acquire) {
+        return Err(RtcError::NotInitialized);
+    }
+    let rtc = read_rtc()?;
+    let lag = RTC_WRITE_LAG_MICROS.load(Ordering::Acquire);
+    Ok(micros_between(rtc, now) - i64::from(lag))
+}
+
+/// Apply a frequency correction through RTC smooth calibration (RTC_CALR)
+///
+/// `freq_ppb` is the total correction, positive to speed the RTC up. The
+/// hardware works in steps of ~0.95 ppm over a 32 s cycle and clamps to
+/// about ±487 ppm.
+pub fn calibrate_rtc(freq_ppb: i32) -> Result<(), RtcError> {
+    critical_section::with(|cs| {
+        if let Some(rtc) = RTC.borrow(cs).borrow_mut().as_mut() {
+            rtc.calibrate(
+                freq_ppb as f32 / 1000.0,
+                RtcCalibrationCyclePeriod::Seconds32,
+            );
+            Ok(())
         } else {
             Err(RtcError::NotInitialized)
         }
     })
 }
 
+/// Microseconds from `earlier` to `later` (negative if reversed)
+fn micros_between(earlier: Timestamp, later: Timestamp) -> i64 {
+    let secs = later.unix_secs as i64 - earlier.unix_secs as i64;
+    secs * 1_000_000 + i64::from(later.micros) - i64::from(earlier.micros)
+}
+
 /// Get current timestamp from internal RTC hardware
 #[allow(dead_code)]
 pub fn get_timestamp() -> Timestamp {
@@ -117,6 +178,14 @@ mod tests {
         assert_eq!(ts.micros, 0);
     }
 
+    #[test]
+    fn test_micros_between() {
+        let earlier = Timestamp::new(1704067200, 900_000);
+        let later = Timestamp::new(1704067201, 100_000);
+        assert_eq!(micros_between(earlier, later), 200_000);
+        assert_eq!(micros_between(later, earlier), -200_000);
+    }
+
     #[test]
     fn test_timestamp_creation() {
         let ts = Timestamp::new(1704067200, 500000);
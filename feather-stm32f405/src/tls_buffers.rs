@@ -7,130 +7,225 @@
 //! # Design Rationale
 //!
 //! TLS buffers are placed in main SRAM (128KB available) rather than CCM RAM (64KB) because:
-//! 1. **Size Requirements**: TLS 1.3 requires 17KB+ read buffer, 16KB write buffer
+//! 1. **Size Requirements**: a full 16 KB TLS record needs ~16.3 KB per direction
 //! 2. **CCM RAM Conservation**: CCM RAM is better used for critical timing-sensitive data
 //! 3. **Performance**: TLS crypto operations are compute-bound, not memory-bound
 //! 4. **Flexibility**: Main SRAM has more space for future expansion
 //!
-//! # Buffer Sizing
+//! # Buffer Sizing (SR-PERF-007)
 //!
-//! **Read Buffer (18 KB)**:
-//! - TLS 1.3 maximum plaintext: 16384 bytes (16 KB)
-//! - TLS record header: 5 bytes
-//! - AEAD authentication tag: 16 bytes (AES-128-GCM-SHA256)
-//! - Padding allowance: ~512 bytes for safety
-//! - **Total**: 17 KB minimum, using 18 KB for alignment and safety margin
+//! Each buffer holds one complete TLS record: the plaintext fragment plus
+//! `RECORD_OVERHEAD` (5-byte header and up to 256 bytes of TLS 1.3 ciphertext
+//! expansion: content type, padding and AEAD tag).
 //!
-//! **Write Buffer (16 KB)**:
-//! - We control outgoing record sizes, so 16 KB is sufficient
-//! - Matches TLS 1.3 maximum record size
+//! The client negotiates the Max Fragment Length extension (RFC 6066) with
+//! `FRAGMENT_LENGTH`, so the peer never sends records larger than that and
+//! both buffers shrink accordingly:
 //!
-//! # Safety
+//! | `FRAGMENT_LENGTH`  | Read      | Write     | Total     |
+//! |--------------------|-----------|-----------|-----------|
+//! | `None` (16384)     | 16 645 B  | 16 645 B  | ~32.5 KB  |
+//! | `Bytes4096`        | 4 357 B   | 4 357 B   | ~8.5 KB   |
+//! | `Bytes2048`        | 2 309 B   | 2 309 B   | ~4.5 KB   |
 //!
-//! These buffers use `static mut` which is unsafe. Safety is ensured by:
-//! - Single accessor functions that return mutable references
-//! - Documentation requiring single-use semantics
-//! - No concurrent access (enforced by borrow checker at call site)
+//! MQTT telemetry fits easily in 4 KB records; the broker certificate chain
+//! is the largest message and must fit one fragment too. Servers that ignore
+//! the extension are reported as `TlsError::FragmentLengthRefused`; set
+//! `FRAGMENT_LENGTH` to `None` to talk to them.
+//!
+//! # Ownership
+//!
+//! A `TlsBufferPool` owns one read and one write buffer, sized by its const
+//! generic parameters. `acquire()` atomically claims both buffers and returns
+//! a `TlsBuffers` value holding the slices and a `TlsBuffersToken`. The token
+//! hands the buffers back when dropped, so they can be claimed again for the
+//! next connection. While the token is alive, any further `acquire()` fails
+//! with `TlsError::BuffersInUse` instead of aliasing the same memory.
+//!
+//! Splitting the claim with `into_parts()` is `unsafe`: the slices are
+//! `'static` so they can live inside a long-lived connection, and only the
+//! caller can ensure they are gone before the token is dropped.
+//!
+//! ```no_run
+//! let buffers = tls_buffers::acquire()?;
+//! // SAFETY: `token` is stored next to `connection` and dropped after it
+//! let (read_buf, write_buf, token) = unsafe { buffers.into_parts() };
+//! let connection = TlsConnection::new(socket, read_buf, write_buf);
+//! ```
 
-#![allow(unsafe_code)] // Required for static mut buffers
+#![allow(unsafe_code)] // Required for the shared static buffers
 #![deny(warnings)]
 
-/// TLS read buffer size: 18 KB
-///
-/// Sized to handle maximum TLS 1.3 record (16384 bytes) plus all overhead:
-/// - Record header: 5 bytes
-/// - AEAD tag: 16 bytes
-/// - Padding/safety: 512 bytes
-const TLS_READ_BUF_SIZE: usize = 18 * 1024; // 18432 bytes
+use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicBool, Ordering};
 
-/// TLS write buffer size: 16 KB
-///
-/// Maximum TLS 1.3 record size for outgoing data
-const TLS_WRITE_BUF_SIZE: usize = 16 * 1024; // 16384 bytes
+use crate::network::error::TlsError;
+use crate::network::tls::FragmentLength;
 
-/// TLS read buffer in main SRAM
-///
-/// Used for receiving TLS records from the network.
-///
-/// # Safety
-/// - Must only be accessed once per TLS connection lifetime
-/// - No DMA access required (CPU-only processing)
-/// - No concurrent access (enforced by Rust borrow rules at call site)
-static mut TLS_READ_BUF: [u8; TLS_READ_BUF_SIZE] = [0; TLS_READ_BUF_SIZE];
+/// Largest TLS plaintext fragment without Max Fragment Length (2^14)
+pub const MAX_PLAINTEXT_LEN: usize = 16384;
 
-/// TLS write buffer in main SRAM
-///
-/// Used for sending TLS records to the network.
+/// Bytes a TLS 1.3 record adds around its plaintext
 ///
-/// # Safety
-/// - Must only be accessed once per TLS connection lifetime
-/// - No DMA access required (CPU-only processing)
-/// - No concurrent access (enforced by Rust borrow rules at call site)
-static mut TLS_WRITE_BUF: [u8; TLS_WRITE_BUF_SIZE] = [0; TLS_WRITE_BUF_SIZE];
+/// 5-byte record header plus the maximum ciphertext expansion of 256 bytes
+/// (inner content type, padding and AEAD tag, RFC 8446 §5.2).
+pub const RECORD_OVERHEAD: usize = 5 + 256;
 
-/// Get mutable reference to TLS read buffer
-///
-/// # Safety
-///
-/// This function returns a mutable reference to a static buffer. The caller must ensure:
-/// - The buffer is used by only one TLS connection at a time
-/// - The buffer is not accessed concurrently from multiple contexts
-/// - The buffer reference does not outlive the TLS connection
-///
-/// # Usage
-///
-/// ```no_run
-/// let read_buf = unsafe { tls_read_buffer() };
-/// // Use read_buf for exactly one TLS connection
-/// // Buffer becomes available again when connection closes
-/// ```
-#[allow(dead_code)] // May be used directly in future
-pub unsafe fn tls_read_buffer() -> &'static mut [u8] {
-    // SAFETY: Caller must ensure single-use semantics
-    // Raw pointer dereference required per Rust 2024 edition
-    &mut *core::ptr::addr_of_mut!(TLS_READ_BUF)
+/// Max Fragment Length the firmware negotiates (`None` = standard 16 KB)
+pub const FRAGMENT_LENGTH: Option<FragmentLength> = Some(FragmentLength::Bytes4096);
+
+/// Plaintext bytes per record the static buffers are sized for
+pub const FRAGMENT_LEN: usize = match FRAGMENT_LENGTH {
+    Some(length) => length.bytes(),
+    None => MAX_PLAINTEXT_LEN,
+};
+
+/// Buffer size holding one complete record with `fragment_len` plaintext bytes
+pub const fn record_buffer_size(fragment_len: usize) -> usize {
+    fragment_len + RECORD_OVERHEAD
 }
 
-/// Get mutable reference to TLS write buffer
-///
-/// # Safety
-///
-/// This function returns a mutable reference to a static buffer. The caller must ensure:
-/// - The buffer is used by only one TLS connection at a time
-/// - The buffer is not accessed concurrently from multiple contexts
-/// - The buffer reference does not outlive the TLS connection
-///
-/// # Usage
+/// TLS read buffer size for `FRAGMENT_LENGTH`
+const TLS_READ_BUF_SIZE: usize = record_buffer_size(FRAGMENT_LEN);
+
+/// TLS write buffer size for `FRAGMENT_LENGTH`
 ///
-/// ```no_run
-/// let write_buf = unsafe { tls_write_buffer() };
-/// // Use write_buf for exactly one TLS connection
-/// // Buffer becomes available again when connection closes
-/// ```
-#[allow(dead_code)] // May be used directly in future
-pub unsafe fn tls_write_buffer() -> &'static mut [u8] {
-    // SAFETY: Caller must ensure single-use semantics
-    // Raw pointer dereference required per Rust 2024 edition
-    &mut *core::ptr::addr_of_mut!(TLS_WRITE_BUF)
+/// Outgoing records must also respect the negotiated limit, so the write
+/// buffer is sized the same way.
+const TLS_WRITE_BUF_SIZE: usize = record_buffer_size(FRAGMENT_LEN);
+
+/// Buffers used by `SecureConnector`, in main SRAM (.bss)
+static TLS_BUFFERS: TlsBufferPool<TLS_READ_BUF_SIZE, TLS_WRITE_BUF_SIZE> = TlsBufferPool::new();
+
+/// Statically allocated read/write buffer pair for one TLS connection
+///
+/// `READ` and `WRITE` are the buffer sizes in bytes; use `record_buffer_size`
+/// to derive them from a fragment length.
+pub struct TlsBufferPool<const READ: usize, const WRITE: usize> {
+    read: UnsafeCell<[u8; READ]>,
+    write: UnsafeCell<[u8; WRITE]>,
+    /// Set while a `TlsBuffersToken` for this pool is alive
+    taken: AtomicBool,
 }
 
-/// Get both TLS buffers (read and write) as a tuple
-///
-/// Convenience function for TLS connection setup.
-///
-/// # Safety
-///
-/// Same safety requirements as individual buffer accessors. The caller must ensure:
-/// - Buffers are used by only one TLS connection at a time
-/// - No concurrent access
-/// - Buffer references don't outlive the TLS connection
+// SAFETY: The buffers are only reachable through `acquire()`, which hands
+// out at most one pair of references at a time (guarded by `taken`).
+unsafe impl<const READ: usize, const WRITE: usize> Sync for TlsBufferPool<READ, WRITE> {}
+
+impl<const READ: usize, const WRITE: usize> TlsBufferPool<READ, WRITE> {
+    /// Create a pool with zeroed buffers (placed in .bss when `static`)
+    pub const fn new() -> Self {
+        Self {
+            read: UnsafeCell::new([0; READ]),
+            write: UnsafeCell::new([0; WRITE]),
+            taken: AtomicBool::new(false),
+        }
+    }
+
+    /// Claim the read and write buffers for one connection
+    ///
+    /// # Errors
+    ///
+    /// Returns `TlsError::BuffersInUse` if another connection still holds them.
+    pub fn acquire(&'static self) -> Result<TlsBuffers, TlsError> {
+        if self.taken.swap(true, Ordering::Acquire) {
+            return Err(TlsError::BuffersInUse);
+        }
+
+        // SAFETY: The atomic flag above guarantees that no other `TlsBuffers`
+        // for this pool exists until the token created here is dropped, so
+        // these are the only live references to the buffers.
+        let (read, write) = unsafe { (&mut *self.read.get(), &mut *self.write.get()) };
+
+        Ok(TlsBuffers {
+            read,
+            write,
+            token: TlsBuffersToken { taken: &self.taken },
+        })
+    }
+}
+
+impl<const READ: usize, const WRITE: usize> Default for TlsBufferPool<READ, WRITE> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Exclusive claim on the TLS buffers
+///
+/// Returned by `acquire()`; split it with `into_parts()` to hand the slices to
+/// a TLS connection while keeping the token next to it.
+pub struct TlsBuffers {
+    read: &'static mut [u8],
+    write: &'static mut [u8],
+    token: TlsBuffersToken,
+}
+
+impl TlsBuffers {
+    /// Split into `(read_buffer, write_buffer, token)`
+    ///
+    /// # Safety
+    ///
+    /// Dropping the token releases the buffers, after which `acquire()`
+    /// hands out new references to the same memory. The caller must ensure
+    /// the returned slices (and anything borrowing them) are dropped before
+    /// the token, e.g. by storing the token in the same struct as the
+    /// connection, declared after it.
+    pub unsafe fn into_parts(self) -> (&'static mut [u8], &'static mut [u8], TlsBuffersToken) {
+        (self.read, self.write, self.token)
+    }
+}
+
+/// Proof of ownership of the TLS buffers; releases them when dropped
+pub struct TlsBuffersToken {
+    taken: &'static AtomicBool,
+}
+
+impl Drop for TlsBuffersToken {
+    fn drop(&mut self) {
+        self.taken.store(false, Ordering::Release);
+    }
+}
+
+/// Claim the connector's TLS read and write buffers for one connection
 ///
-/// # Returns
+/// # Errors
 ///
-/// `(read_buffer, write_buffer)` - Tuple of mutable slices
-pub unsafe fn tls_buffers() -> (&'static mut [u8], &'static mut [u8]) {
-    (
-        &mut *core::ptr::addr_of_mut!(TLS_READ_BUF),
-        &mut *core::ptr::addr_of_mut!(TLS_WRITE_BUF),
-    )
+/// Returns `TlsError::BuffersInUse` if another connection still holds them.
+pub fn acquire() -> Result<TlsBuffers, TlsError> {
+    TLS_BUFFERS.acquire()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_second_acquire_fails_until_release() {
+        // SAFETY: the slices are not used after the token is dropped
+        let (read, write, token) = unsafe { acquire().unwrap().into_parts() };
+        assert_eq!(read.len(), TLS_READ_BUF_SIZE);
+        assert_eq!(write.len(), TLS_WRITE_BUF_SIZE);
+
+        assert!(matches!(acquire(), Err(TlsError::BuffersInUse)));
+
+        drop(token);
+        assert!(acquire().is_ok());
+    }
+
+    #[test]
+    fn test_pool_sizes_follow_const_parameters() {
+        static POOL: TlsBufferPool<{ record_buffer_size(512) }, 64> = TlsBufferPool::new();
+
+        // SAFETY: the slices go out of scope together with the token
+        let (read, write, _token) = unsafe { POOL.acquire().unwrap().into_parts() };
+        assert_eq!(read.len(), 512 + RECORD_OVERHEAD);
+        assert_eq!(write.len(), 64);
+    }
+
+    #[test]
+    fn test_fragment_length_shrinks_buffers() {
+        assert_eq!(record_buffer_size(MAX_PLAINTEXT_LEN), 16645);
+        assert_eq!(record_buffer_size(FragmentLength::Bytes4096.bytes()), 4357);
+    }
 }